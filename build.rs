@@ -10,8 +10,8 @@ use std::process::exit;
 use rustc_version::version_matches;
 
 fn main() {
-  if !version_matches(">= 1.8.0") {
-    writeln!(&mut io::stderr(), "bins requires at least Rust 1.8.0").unwrap();
+  if !version_matches(">= 1.21.0") {
+    writeln!(&mut io::stderr(), "bins requires at least Rust 1.21.0").unwrap();
     exit(1);
   }
   let profile = env::var("PROFILE").unwrap();
@@ -3,17 +3,83 @@ use clap::{App, Arg};
 use toml::Value;
 use bins::configuration::BetterLookups;
 use bins::engines;
+use bins::expiration::Expiration;
+use bins::output::OutputMode;
 use bins::error::*;
 
 pub struct Arguments {
   pub files: Vec<String>,
   pub message: Option<String>,
-  pub service: Option<String>,
+  /// The services to paste to, all at once if there are several.
+  pub services: Vec<String>,
   pub private: bool,
   pub auth: bool,
   pub copy: bool,
   pub input: Option<String>,
-  pub nth: Option<usize>
+  pub nth: Option<usize>,
+  pub delete: Option<String>,
+  pub update: Option<String>,
+  pub remove: Vec<String>,
+  pub expire: Option<Expiration>,
+  pub lang: Option<String>,
+  pub title: Option<String>,
+  pub history: bool,
+  pub search: Option<String>,
+  pub recall: Option<usize>,
+  pub timeout: Option<u64>,
+  pub output: OutputMode,
+  pub format: Option<String>,
+  pub allow_secrets: bool,
+  pub burn: bool,
+  pub password: Option<String>,
+  pub recursive: bool,
+  pub hidden: bool,
+  pub no_ignore: bool,
+  pub output_dir: Option<String>,
+  pub output_file: Option<String>,
+  pub force: bool,
+  /// The services to try in order if pasting to the first one fails.
+  pub fallback: Vec<String>,
+  /// The url of a paste to paste again on another service.
+  pub migrate: Option<String>
+}
+
+impl Default for Arguments {
+  fn default() -> Self {
+    Arguments {
+      files: Vec::new(),
+      message: None,
+      services: Vec::new(),
+      private: true,
+      auth: true,
+      copy: false,
+      input: None,
+      nth: None,
+      delete: None,
+      update: None,
+      remove: Vec::new(),
+      expire: None,
+      lang: None,
+      title: None,
+      history: false,
+      search: None,
+      recall: None,
+      timeout: None,
+      output: OutputMode::Text,
+      format: None,
+      allow_secrets: false,
+      burn: false,
+      password: None,
+      recursive: false,
+      hidden: false,
+      no_ignore: false,
+      output_dir: None,
+      output_file: None,
+      force: false,
+      fallback: Vec::new(),
+      migrate: None
+    }
+  }
 }
 
 include!(concat!(env!("OUT_DIR"), "/git_short_tag.rs"));
@@ -48,24 +114,49 @@ fn get_clipboard_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
 
 pub fn get_arguments(config: &Value) -> Result<Arguments> {
   let mut arguments = Arguments {
-    files: Vec::new(),
-    message: None,
-    service: config.lookup_str("defaults.service").map(|s| s.to_owned()),
+    services: config.lookup_str("defaults.service")
+      .map_or_else(Vec::new, |s| s.split(',').map(|s| s.trim().to_owned()).filter(|s| !s.is_empty()).collect()),
     private: config.lookup_bool_or("defaults.private", true),
     auth: config.lookup_bool_or("defaults.auth", true),
     copy: config.lookup_bool_or("defaults.copy", false),
-    input: None,
-    nth: None
+    expire: match config.lookup_str("defaults.expire") {
+      Some(e) => Some(try!(Expiration::parse(e))),
+      None => None,
+    },
+    format: config.lookup_str("defaults.format").map(|s| s.to_owned()),
+    ..Arguments::default()
   };
+  if let Some(fallback) = config.lookup("defaults.fallback") {
+    let fallback = some_or_err!(fallback.as_slice(), "defaults.fallback was not an array".into());
+    for service in fallback {
+      let service = some_or_err!(service.as_str(), "defaults.fallback must only contain strings".into());
+      arguments.fallback.push(service.to_owned());
+    }
+  }
+  let engines = try!(engines::get_engines(config));
+  let engine_names = engines::get_engine_names(&engines);
   let name = get_name();
   let version = get_version();
   let mut app = App::new(name.as_ref())
     .version(version.as_ref())
     .about("A command-line pastebin client")
     .arg(Arg::with_name("files")
-      .help("files to paste")
+      .help("files to paste, or quoted glob patterns like 'src/**/*.rs'")
       .takes_value(true)
       .multiple(true))
+    .arg(Arg::with_name("recursive")
+      .short("r")
+      .long("recursive")
+      .help("pastes the text files in directories, skipping files ignored by .gitignore or .ignore")
+      .requires("files"))
+    .arg(Arg::with_name("hidden")
+      .long("hidden")
+      .help("includes hidden files in directories and glob patterns")
+      .requires("files"))
+    .arg(Arg::with_name("no-ignore")
+      .long("no-ignore")
+      .help("includes files ignored by .gitignore or .ignore in directories and glob patterns")
+      .requires("files"))
     .arg(Arg::with_name("message")
       .short("m")
       .long("message")
@@ -94,15 +185,27 @@ pub fn get_arguments(config: &Value) -> Result<Arguments> {
     .arg(Arg::with_name("service")
       .short("s")
       .long("service")
-      .help("pastebin service to use")
+      .help("pastebin service to use, or several separated by commas to paste to all of them")
+      .takes_value(true)
+      .multiple(true)
+      .require_delimiter(true)
+      .value_name("service")
+      .possible_values(&engine_names)
+      .required(arguments.services.is_empty()))
+    .arg(Arg::with_name("fallback")
+      .long("fallback")
+      .help("services to try in order if pasting fails, separated by commas")
       .takes_value(true)
-      .possible_values(&*engines::get_engine_names())
-      .required(arguments.service.is_none()))
+      .multiple(true)
+      .require_delimiter(true)
+      .value_name("service")
+      .possible_values(&engine_names)
+      .conflicts_with_all(&["input", "delete", "update"]))
     .arg(Arg::with_name("list-services")
       .short("l")
       .long("list-services")
       .help("lists available bins and exits")
-      .conflicts_with_all(&["files", "message", "private", "public", "auth", "anon", "service", "input"]))
+      .conflicts_with_all(&["files", "message", "private", "public", "auth", "anon", "service", "input", "delete", "update"]))
     .arg(Arg::with_name("input")
       .short("i")
       .long("input")
@@ -117,13 +220,139 @@ pub fn get_arguments(config: &Value) -> Result<Arguments> {
       .takes_value(true)
       .value_name("index")
       .requires("input")
-      .conflicts_with("files"));
+      .conflicts_with("files"))
+    .arg(Arg::with_name("output-dir")
+      .long("output-dir")
+      .help("downloads every file of the input paste into a directory")
+      .takes_value(true)
+      .value_name("dir")
+      .requires("input")
+      .conflicts_with_all(&["files", "nth"]))
+    .arg(Arg::with_name("output-file")
+      .short("o")
+      .long("output-file")
+      .help("writes the input paste to a file, or into a directory under its name in the paste")
+      .takes_value(true)
+      .value_name("path")
+      .requires("input")
+      .conflicts_with("output-dir"))
+    .arg(Arg::with_name("force")
+      .long("force")
+      .help("overwrites existing files with --output-dir or --output-file"))
+    .arg(Arg::with_name("migrate")
+      .long("migrate")
+      .help("pastes every file of the paste at the given url again on the chosen service, keeping their names")
+      .takes_value(true)
+      .value_name("url")
+      .conflicts_with_all(&["files", "message", "input", "delete", "update", "lang"]))
+    .arg(Arg::with_name("delete")
+      .short("d")
+      .long("delete")
+      .help("deletes the paste at the given url")
+      .takes_value(true)
+      .value_name("url")
+      .conflicts_with_all(&["files", "auth", "anon", "public", "private", "message", "service", "input"]))
+    .arg(Arg::with_name("update")
+      .short("u")
+      .long("update")
+      .help("updates the paste at the given url instead of creating a new one")
+      .takes_value(true)
+      .value_name("url")
+      .conflicts_with_all(&["public", "private", "service", "input", "delete"]))
+    .arg(Arg::with_name("remove")
+      .long("remove")
+      .help("files to remove from the paste in update mode")
+      .takes_value(true)
+      .multiple(true)
+      .number_of_values(1)
+      .value_name("file")
+      .requires("update"))
+    .arg(Arg::with_name("expire")
+      .short("e")
+      .long("expire")
+      .help("how long the paste should last, like 10m, 1h, 1d, 1w or never")
+      .takes_value(true)
+      .value_name("duration")
+      .conflicts_with_all(&["input", "delete"]))
+    .arg(Arg::with_name("title")
+      .short("t")
+      .long("title")
+      .help("title or description of the paste")
+      .takes_value(true)
+      .value_name("string")
+      .conflicts_with_all(&["input", "delete", "description"]))
+    .arg(Arg::with_name("description")
+      .long("description")
+      .help("same as --title")
+      .takes_value(true)
+      .value_name("string")
+      .conflicts_with_all(&["input", "delete"]))
+    .arg(Arg::with_name("lang")
+      .short("L")
+      .long("lang")
+      .help("language to highlight the paste as, instead of detecting it from file extensions")
+      .takes_value(true)
+      .value_name("language")
+      .conflicts_with_all(&["input", "delete"]))
+    .arg(Arg::with_name("timeout")
+      .long("timeout")
+      .help("seconds to wait for a service to connect or respond before giving up (0 to wait forever)")
+      .takes_value(true)
+      .value_name("seconds"))
+    .arg(Arg::with_name("output")
+      .long("output")
+      .help("how to print the result: text, or json for scripts")
+      .takes_value(true)
+      .value_name("mode")
+      .possible_values(&["text", "json"])
+      .conflicts_with_all(&["delete", "update", "history", "search", "recall"]))
+    .arg(Arg::with_name("format")
+      .long("format")
+      .help("template for the url of a new paste, using {url}, {raw_url}, {service}, {files} and {title}, or one of \
+             the presets markdown, html or org")
+      .takes_value(true)
+      .value_name("template")
+      .conflicts_with_all(&["output", "input", "delete", "update", "history", "search", "recall"]))
+    .arg(Arg::with_name("allow-secrets")
+      .long("allow-secrets")
+      .help("pastes files even if they look like they contain passwords, tokens or keys")
+      .conflicts_with_all(&["input", "delete", "history", "search", "recall"]))
+    .arg(Arg::with_name("burn")
+      .long("burn")
+      .help("deletes the paste after it has been read once (privatebin)")
+      .conflicts_with_all(&["input", "delete", "update", "history", "search", "recall"]))
+    .arg(Arg::with_name("password")
+      .long("password")
+      .help("password to encrypt the paste with, or to decrypt it in input mode (privatebin)")
+      .takes_value(true)
+      .value_name("password")
+      .conflicts_with_all(&["delete", "update", "history", "search", "recall"]));
+  let history_conflicts = ["files", "message", "private", "public", "auth", "anon", "service", "input", "delete",
+                           "update", "expire", "title", "description", "lang", "migrate"];
+  app = app.arg(Arg::with_name("history")
+      .short("H")
+      .long("history")
+      .help("lists pastes made previously")
+      .conflicts_with_all(&history_conflicts))
+    .arg(Arg::with_name("search")
+      .long("search")
+      .help("lists pastes made previously with a matching file name or service")
+      .takes_value(true)
+      .value_name("query")
+      .conflicts_with_all(&history_conflicts))
+    .arg(Arg::with_name("recall")
+      .long("recall")
+      .help("displays the url of a paste made previously, by its number in the history")
+      .takes_value(true)
+      .value_name("number")
+      .conflicts_with_all(&history_conflicts)
+      .conflicts_with("search"));
   for arg in get_clipboard_args() {
     app = app.arg(arg);
   }
   let res = app.get_matches();
   if res.is_present("list-services") {
-    println!("gist\nhastebin\npastebin\npastie\nsprunge");
+    println!("{}", engine_names.join("\n"));
     process::exit(0);
   }
   if let Some(files) = res.values_of("files") {
@@ -132,12 +361,69 @@ pub fn get_arguments(config: &Value) -> Result<Arguments> {
   if let Some(message) = res.value_of("message") {
     arguments.message = Some(message.to_owned());
   }
-  if let Some(service) = res.value_of("service") {
-    arguments.service = Some(service.to_owned());
+  if let Some(services) = res.values_of("service") {
+    arguments.services = services.map(|s| s.to_owned()).collect();
+  }
+  if let Some(fallback) = res.values_of("fallback") {
+    arguments.fallback = fallback.map(|s| s.to_owned()).collect();
   }
   if let Some(input) = res.value_of("input") {
     arguments.input = Some(input.to_owned());
   }
+  if let Some(migrate) = res.value_of("migrate") {
+    arguments.migrate = Some(migrate.to_owned());
+  }
+  if let Some(delete) = res.value_of("delete") {
+    arguments.delete = Some(delete.to_owned());
+  }
+  if let Some(update) = res.value_of("update") {
+    arguments.update = Some(update.to_owned());
+  }
+  if let Some(remove) = res.values_of("remove") {
+    arguments.remove = remove.map(|s| s.to_owned()).collect();
+  }
+  if let Some(expire) = res.value_of("expire") {
+    arguments.expire = Some(try!(Expiration::parse(expire)));
+  }
+  if let Some(title) = res.value_of("title").or(res.value_of("description")) {
+    arguments.title = Some(title.to_owned());
+  }
+  if let Some(lang) = res.value_of("lang") {
+    arguments.lang = Some(lang.to_owned());
+  }
+  if let Some(search) = res.value_of("search") {
+    arguments.search = Some(search.to_owned());
+  }
+  if let Some(recall) = res.value_of("recall") {
+    let recall = try!(recall.parse::<usize>().map_err(|_| "recall argument was not a number"));
+    arguments.recall = Some(recall);
+  }
+  arguments.allow_secrets = res.is_present("allow-secrets");
+  arguments.burn = res.is_present("burn");
+  arguments.recursive = res.is_present("recursive");
+  arguments.hidden = res.is_present("hidden");
+  arguments.no_ignore = res.is_present("no-ignore");
+  arguments.force = res.is_present("force");
+  if let Some(output_dir) = res.value_of("output-dir") {
+    arguments.output_dir = Some(output_dir.to_owned());
+  }
+  if let Some(output_file) = res.value_of("output-file") {
+    arguments.output_file = Some(output_file.to_owned());
+  }
+  if let Some(password) = res.value_of("password") {
+    arguments.password = Some(password.to_owned());
+  }
+  if let Some(format) = res.value_of("format") {
+    arguments.format = Some(format.to_owned());
+  }
+  if let Some(output) = res.value_of("output") {
+    arguments.output = try!(OutputMode::parse(output));
+  }
+  if let Some(timeout) = res.value_of("timeout") {
+    let timeout = try!(timeout.parse::<u64>().map_err(|_| "timeout argument was not a number"));
+    arguments.timeout = Some(timeout);
+  }
+  arguments.history = res.is_present("history") || arguments.search.is_some() || arguments.recall.is_some();
   if let Some(nth) = res.value_of("nth") {
     let nth = try!(nth.parse::<usize>().map_err(|_| "nth argument was not a number"));
     arguments.nth = Some(nth);
@@ -0,0 +1,167 @@
+use bins::error::*;
+use bins::PasteFile;
+use rustc_serialize::base64::{self, FromBase64, ToBase64};
+use std::str;
+
+pub const TEXT: &'static str = "text/plain; charset=utf-8";
+
+pub const OCTET_STREAM: &'static str = "application/octet-stream";
+
+const MAGIC: &'static [(&'static [u8], &'static str)] = &[(b"\x1f\x8b", "application/gzip"),
+                                                          (b"PK\x03\x04", "application/zip"),
+                                                          (b"\x89PNG\r\n\x1a\n", "image/png"),
+                                                          (b"\xff\xd8\xff", "image/jpeg"),
+                                                          (b"GIF8", "image/gif"),
+                                                          (b"%PDF-", "application/pdf"),
+                                                          (b"\x7fELF", "application/x-elf"),
+                                                          (b"\xfd7zXZ\x00", "application/x-xz"),
+                                                          (b"BZh", "application/x-bzip2"),
+                                                          (b"\x28\xb5\x2f\xfd", "application/zstd")];
+
+const BEGIN: &'static str = "-----BEGIN BINS BINARY FILE-----";
+
+const END: &'static str = "-----END BINS BINARY FILE-----";
+
+/// Detects the MIME type of a file from its contents. Anything that is valid UTF-8 without null bytes is text.
+pub fn detect_mime(data: &[u8]) -> &'static str {
+  if !data.contains(&0) && str::from_utf8(data).is_ok() {
+    return TEXT;
+  }
+  MAGIC.iter().find(|m| data.starts_with(m.0)).map_or(OCTET_STREAM, |m| m.1)
+}
+
+/// Whether the first bytes of a file show that it's binary. The bytes may end in the middle of a character.
+pub fn starts_binary(start: &[u8]) -> bool {
+  if start.contains(&0) {
+    return true;
+  }
+  match str::from_utf8(start) {
+    Ok(_) => false,
+    Err(e) => e.error_len().is_some(),
+  }
+}
+
+/// Encodes a binary file as base64 between markers, so it can be pasted to services that only accept text.
+pub fn wrap(file: &PasteFile) -> PasteFile {
+  let config = base64::Config {
+    char_set: base64::CharacterSet::Standard,
+    newline: base64::Newline::LF,
+    pad: true,
+    line_length: Some(76)
+  };
+  let wrapped = format!("{}\nContent-Type: {}\n\n{}\n{}\n",
+                        BEGIN,
+                        file.mime,
+                        file.data.to_base64(config),
+                        END);
+  PasteFile {
+    name: file.name.clone(),
+    data: wrapped.into_bytes(),
+    mime: TEXT,
+    language: None
+  }
+}
+
+fn is_wrapped(data: &[u8]) -> bool {
+  str::from_utf8(data).map(|t| t.trim_left().starts_with(BEGIN)).unwrap_or(false)
+}
+
+/// Decodes a file wrapped by `wrap`, returning anything else as it is.
+pub fn unwrap(data: Vec<u8>) -> Result<Vec<u8>> {
+  if !is_wrapped(&data) {
+    return Ok(data);
+  }
+  // some services change the line endings of pastes
+  let text = String::from_utf8_lossy(&data).replace("\r\n", "\n");
+  let text = text.trim();
+  if text.len() < BEGIN.len() + END.len() || !text.ends_with(END) {
+    return Err("binary paste was cut off".into());
+  }
+  let body = &text[BEGIN.len()..text.len() - END.len()];
+  // headers come first, separated from the data by an empty line
+  let encoded = match body.find("\n\n") {
+    Some(i) => &body[i + 2..],
+    None => body,
+  };
+  encoded.from_base64().map_err(|e| format!("could not decode binary paste: {}", e).into())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{OCTET_STREAM, TEXT, detect_mime, starts_binary, unwrap, wrap};
+  use bins::PasteFile;
+
+  fn binary_data() -> Vec<u8> {
+    // every byte value, twice, so the data isn't UTF-8 and spans several lines of base64
+    (0..512).map(|b| b as u8).collect()
+  }
+
+  fn wrapped() -> PasteFile {
+    wrap(&PasteFile::new(String::from("data.bin"), binary_data()))
+  }
+
+  #[test]
+  fn wrap_unwrap_round_trip() {
+    let wrapped = wrapped();
+    assert_eq!(wrapped.mime, TEXT);
+    assert!(String::from_utf8(wrapped.data.clone()).is_ok());
+    assert_eq!(unwrap(wrapped.data).unwrap(), binary_data());
+  }
+
+  #[test]
+  fn unwrap_with_crlf_line_endings() {
+    let crlf = String::from_utf8(wrapped().data).unwrap().replace('\n', "\r\n");
+    assert_eq!(unwrap(crlf.into_bytes()).unwrap(), binary_data());
+  }
+
+  #[test]
+  fn unwrap_leaves_other_data_alone() {
+    assert_eq!(unwrap(b"just text".to_vec()).unwrap(), b"just text");
+    assert_eq!(unwrap(vec![0xff, 0x00, 0xfe]).unwrap(), vec![0xff, 0x00, 0xfe]);
+  }
+
+  #[test]
+  fn unwrap_rejects_truncated_pastes() {
+    let data = wrapped().data;
+    for &length in &[40, data.len() / 2, data.len() - 10] {
+      assert!(unwrap(data[..length].to_vec()).is_err());
+    }
+    // the markers overlap, so there's nothing between them
+    let overlapping = "-----BEGIN BINS BINARY FILE-----END BINS BINARY FILE-----";
+    assert!(unwrap(overlapping.as_bytes().to_vec()).is_err());
+  }
+
+  #[test]
+  fn unwrap_rejects_corrupted_pastes() {
+    let corrupted = String::from_utf8(wrapped().data).unwrap().replacen("AAEC", "A!EC", 1);
+    assert!(unwrap(corrupted.into_bytes()).is_err());
+  }
+
+  #[test]
+  fn detect_mime_by_magic_number() {
+    assert_eq!(detect_mime(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR"), "image/png");
+    assert_eq!(detect_mime(b"\x1f\x8b\x08\x00\x00\x00\x00\x00"), "application/gzip");
+    assert_eq!(detect_mime(b"\x7fELF\x02\x01\x01\x00"), "application/x-elf");
+    assert_eq!(detect_mime(b"\xff\xfe\x00\x01"), OCTET_STREAM);
+    assert_eq!(detect_mime(b"text with a \x00 in it"), OCTET_STREAM);
+  }
+
+  #[test]
+  fn detect_mime_of_text() {
+    assert_eq!(detect_mime(b"hello, world\n"), TEXT);
+    assert_eq!(detect_mime("gr\u{fc}\u{df}e".as_bytes()), TEXT);
+    // text that happens to start like a gif is still text
+    assert_eq!(detect_mime(b"GIF89a is a file format"), TEXT);
+    assert_eq!(detect_mime(b""), TEXT);
+  }
+
+  #[test]
+  fn starts_binary_by_content() {
+    assert!(starts_binary(b"\x89PNG\r\n\x1a\n"));
+    assert!(starts_binary(b"\x7fELF\x02\x01\x01\x00"));
+    assert!(starts_binary(b"text\xffmore text"));
+    assert!(!starts_binary(b"fn main() {}\n"));
+    // the start of a file can end in the middle of a character
+    assert!(!starts_binary(&"gr\u{fc}".as_bytes()[..3]));
+  }
+}
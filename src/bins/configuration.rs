@@ -16,9 +16,41 @@ private = true
 auth = true
 
 # Uncomment this line if you want to set a default service to use with bins. This will make the `--service` option
-# optional and use the configured service if the option is not specified.
+# optional and use the configured service if the option is not specified. Several services separated by commas are
+# all pasted to.
 # service = ""
 
+# Uncomment this line to make pastes expire by default. Use a duration like "10m", "1h", "1d" or "1w", or "never".
+# Services that can't expire pastes will refuse to paste unless this is "never".
+# Using the command-line option `--expire` will change this behavior.
+# expire = "never"
+
+# Uncomment this line to print the url of new pastes using a template instead. {url}, {raw_url}, {service}, {files} and
+# {title} are replaced, and "markdown", "html" and "org" are presets for links in those formats.
+# Using the command-line option `--format` will change this behavior.
+# format = "markdown"
+
+# What to do with files that aren't text when pasting to a service that only accepts text. "wrap" pastes them as
+# base64 between markers, which input mode decodes again, and "refuse" refuses to paste them.
+binary = "wrap"
+
+# The most files and the most bytes that will be pasted at once, to avoid pasting something like target/ by accident
+# when using `--recursive` or glob patterns. Use 0 for no limit.
+max_files = 100
+max_size = 1048576
+
+# Uncomment this line to try other services in order when pasting to a service fails. Services that can't paste with
+# the options given, like private pastes or expiration, are skipped.
+# Using the command-line option `--fallback` will change this behavior.
+# fallback = ["hastebin", "sprunge", "gist"]
+
+# The amount of files uploaded at the same time when pasting multiple files to a service that doesn't support them.
+concurrency = 4
+
+# If this is true, every paste created will be recorded in $XDG_DATA_HOME/bins/history (or
+# $HOME/.local/share/bins/history), which can be viewed with `--history`.
+history = true
+
 # If this is true, all commands will copy their output to the system clipboard.
 # Using the command-line option `--copy` or `--no-copy` will change this behavior.
 copy = false
@@ -31,10 +63,107 @@ username = ""
 # Generate a token from https://github.com/settings/tokens - only the gist permission is necessary
 access_token = ""
 
+# Uncomment these lines to use a GitHub Enterprise instance instead of gist.github.com. base_url is where gists are
+# viewed, and api_url is the API endpoint of the instance.
+# base_url = "https://github.example.com/gist"
+# api_url = "https://github.example.com/api/v3"
+
 [pastebin]
 # The API key for pastebin.com. Learn more: http://pastebin.com/api
 # If this is empty, all paste attempts to the pastebin service will fail.
 api_key = ""
+
+# The user key for your pastebin.com account. Learn more: http://pastebin.com/api#8
+# If this is set and auth is enabled, pastes will be made to your account, which allows deleting them with `--delete`.
+api_user_key = ""
+
+# Uncomment this line to use a pastebin-compatible service instead of pastebin.com.
+# base_url = "http://pastebin.com"
+
+[hastebin]
+# Uncomment this line to use your own haste-server instead of hastebin.com.
+# base_url = "http://hastebin.com"
+
+[pastie]
+# base_url = "http://pastie.org"
+
+[sprunge]
+# base_url = "http://sprunge.us"
+
+[privatebin]
+# Uncomment this line to use your own PrivateBin instance instead of privatebin.net.
+# base_url = "https://privatebin.net"
+
+# If this is true, pastes will be deleted after they have been read once.
+# Using the command-line option `--burn` will enable this for a single paste.
+burn_after_reading = false
+
+[network]
+# Uncomment this line to send all requests through a proxy, like "http://proxy.example.com:3128" or
+# "socks5://127.0.0.1:9050". If this isn't set, the HTTP_PROXY and HTTPS_PROXY environment variables are used. Hosts
+# listed in the NO_PROXY environment variable are never proxied.
+# proxy = ""
+
+# How many times a failed request is tried again. Uploads are only retried if the service asked to try again later, so
+# pastes are never created twice.
+retries = 3
+
+# Milliseconds to wait before retrying a request. This doubles with every retry, and a random part is taken off.
+retry_delay = 500
+
+# The longest time in milliseconds to wait before retrying, even if the service asks to wait longer.
+max_retry_delay = 30000
+
+# Seconds to wait for a connection to a service before giving up, or 0 to wait forever. This isn't used when requests
+# go through an HTTP proxy.
+# Using the command-line option `--timeout` will change this and read_timeout.
+connect_timeout = 10
+
+# Seconds to wait for a service to send or accept data before giving up, or 0 to wait forever.
+read_timeout = 30
+
+[secrets]
+# Files are checked for things like AWS keys, GitHub and GitLab tokens, private keys, JSON web tokens and passwords in
+# .env files before they are pasted. This decides what happens when one is found: "block" refuses to paste, "redact"
+# replaces it with [REDACTED], and "prompt" asks what to do.
+# Using the command-line option `--allow-secrets` pastes files without checking them.
+mode = "block"
+
+# If this is true, long random-looking strings are treated as secrets, too. This also catches things like hashes and
+# encoded data, so it's off by default.
+entropy = false
+
+# Extra regular expressions for secrets. If an expression has a group, only the group is redacted.
+# patterns = ['internal-token-[0-9a-f]{32}']
+
+# Services that aren't built in can be defined here. Each one is a table under `engines`, and its name can be used with
+# `--service`. Uncomment the example below to define an engine named "internal".
+#
+# [engines.internal]
+# # The url files are uploaded to.
+# upload_url = "https://paste.example.com/documents"
+# # The http method used to upload files.
+# method = "POST"
+# # "raw" to send the file as the request body, or "form" to send it in the form field named by `field`.
+# body = "raw"
+# # field = "content"
+# # Where the paste url is found in the response: "body", "json" (using `json_pointer`), "regex" (using the first
+# # group of `regex`) or "location" (the Location header).
+# url_from = "json"
+# json_pointer = "/key"
+# # regex = 'href="([^"]+)"'
+# # An optional template for the paste url, where {value} is replaced by the value found in the response.
+# paste_url = "https://paste.example.com/{value}"
+# # A template for the raw url of a paste, using the {url}, {path} or {id} (the last part of the path) of a paste url.
+# raw_url = "https://paste.example.com/raw/{id}"
+# # The domain of paste urls, used to find this engine in input mode. Defaults to the host of upload_url.
+# # domain = "paste.example.com"
+# # Whether pastes are kept out of public listings, so this engine can be fallen back to for private pastes.
+# # private = false
+#
+# # Extra headers to send when uploading.
+# [engines.internal.headers]
+# X-Api-Key = "secret"
 "#;
 
 
@@ -43,6 +172,8 @@ pub trait BetterLookups {
   fn lookup_str_or<'a>(&'a self, key: &'a str, def: &'a str) -> &'a str;
   fn lookup_bool<'a>(&'a self, path: &'a str) -> Option<bool>;
   fn lookup_bool_or<'a>(&'a self, key: &'a str, def: bool) -> bool;
+  fn lookup_int<'a>(&'a self, path: &'a str) -> Option<i64>;
+  fn lookup_int_or<'a>(&'a self, key: &'a str, def: i64) -> i64;
 }
 
 impl BetterLookups for Value {
@@ -67,6 +198,17 @@ impl BetterLookups for Value {
   fn lookup_bool_or<'a>(&'a self, key: &'a str, def: bool) -> bool {
     self.lookup_bool(key).unwrap_or(def)
   }
+
+  fn lookup_int<'a>(&'a self, path: &'a str) -> Option<i64> {
+    match self.lookup(path) {
+      Some(v) => v.as_integer(),
+      None => None,
+    }
+  }
+
+  fn lookup_int_or<'a>(&'a self, key: &'a str, def: i64) -> i64 {
+    self.lookup_int(key).unwrap_or(def)
+  }
 }
 
 
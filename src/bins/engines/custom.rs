@@ -0,0 +1,361 @@
+use bins::error::*;
+use bins::{Bins, Paste, PasteFile, RawPaste};
+use bins::engines::Engine;
+use bins::engines::indexed::{UploadsIndices, ChecksIndices, IndexedDownload, DownloadsFile};
+use bins::network::RetryPolicy;
+use hyper::header::{Headers, ContentType, Location};
+use hyper::method::Method;
+use hyper::Url;
+use regex::Regex;
+use rustc_serialize::json::Json;
+use std::io::Read;
+use toml::Value;
+use url::form_urlencoded;
+
+enum BodyMode {
+  /// The file contents are the request body.
+  Raw,
+  /// The file contents are sent in the given form field.
+  Form(String)
+}
+
+enum UrlSource {
+  Body,
+  JsonPointer(String),
+  Regex(Regex),
+  Location
+}
+
+/// An engine defined in the `engines` section of the configuration file.
+pub struct CustomEngine {
+  name: String,
+  domain: String,
+  upload_url: String,
+  method: Method,
+  /// Headers from the configuration file, kept as pairs so the engine can be shared between threads.
+  headers: Vec<(String, String)>,
+  body: BodyMode,
+  url_source: UrlSource,
+  paste_url: Option<String>,
+  raw_url: String,
+  private: bool
+}
+
+fn lookup_string(table: &Value, name: &str, key: &str) -> Result<Option<String>> {
+  match table.lookup(key) {
+    Some(v) => {
+      match v.as_str() {
+        Some(s) => Ok(Some(s.to_owned())),
+        None => Err(format!("engines.{}.{} was not a string", name, key).into()),
+      }
+    }
+    None => Ok(None),
+  }
+}
+
+fn require_string(table: &Value, name: &str, key: &str) -> Result<String> {
+  let value = try!(lookup_string(table, name, key));
+  Ok(some_or_err!(value, format!("engines.{}.{} is required", name, key).into()))
+}
+
+/// Follows a JSON pointer (RFC 6901) like `/data/url`.
+fn find_pointer<'a>(json: &'a Json, pointer: &str) -> Option<&'a Json> {
+  if !pointer.is_empty() && !pointer.starts_with('/') {
+    return None;
+  }
+  let mut current = json;
+  for part in pointer.split('/').skip(1) {
+    let part = part.replace("~1", "/").replace("~0", "~");
+    current = match *current {
+      Json::Object(ref map) => {
+        match map.get(&part) {
+          Some(j) => j,
+          None => return None,
+        }
+      }
+      Json::Array(ref array) => {
+        match part.parse::<usize>().ok().and_then(|i| array.get(i)) {
+          Some(j) => j,
+          None => return None,
+        }
+      }
+      _ => return None,
+    };
+  }
+  Some(current)
+}
+
+impl CustomEngine {
+  pub fn from_config(config: &Value) -> Result<Vec<CustomEngine>> {
+    let table = match config.lookup("engines") {
+      Some(t) => some_or_err!(t.as_table(), "engines was not a table".into()),
+      None => return Ok(Vec::new()),
+    };
+    let mut engines = Vec::new();
+    for (name, engine) in table {
+      engines.push(try!(CustomEngine::from_table(name, engine)));
+    }
+    Ok(engines)
+  }
+
+  fn from_table(name: &str, table: &Value) -> Result<CustomEngine> {
+    let upload_url = try!(require_string(table, name, "upload_url"));
+    let domain = match try!(lookup_string(table, name, "domain")) {
+      Some(d) => d,
+      None => {
+        let url = try!(Url::parse(&upload_url).map_err(|e| format!("engines.{}.upload_url: {}", name, e)));
+        some_or_err!(url.host_str(), format!("engines.{}.upload_url had no host", name).into()).to_owned()
+      }
+    };
+    let method_name = try!(lookup_string(table, name, "method")).unwrap_or_else(|| String::from("POST"));
+    let method = try!(method_name.to_uppercase()
+      .parse::<Method>()
+      .map_err(|_| format!("engines.{}.method was not a valid http method", name)));
+    let mut headers = Vec::new();
+    if let Some(header_table) = table.lookup("headers") {
+      let header_table = some_or_err!(header_table.as_table(),
+                                      format!("engines.{}.headers was not a table", name).into());
+      for (header, value) in header_table {
+        let value = some_or_err!(value.as_str(),
+                                 format!("engines.{}.headers.{} was not a string", name, header).into());
+        headers.push((header.clone(), value.to_owned()));
+      }
+    }
+    let body = match try!(lookup_string(table, name, "body")).as_ref().map(|s| &s[..]) {
+      None | Some("raw") => BodyMode::Raw,
+      Some("form") => {
+        BodyMode::Form(try!(lookup_string(table, name, "field")).unwrap_or_else(|| String::from("content")))
+      }
+      Some(b) => return Err(format!("engines.{}.body must be \"raw\" or \"form\", not \"{}\"", name, b).into()),
+    };
+    let url_source = match try!(lookup_string(table, name, "url_from")).as_ref().map(|s| &s[..]) {
+      None | Some("body") => UrlSource::Body,
+      Some("json") => UrlSource::JsonPointer(try!(require_string(table, name, "json_pointer"))),
+      Some("regex") => {
+        let regex = try!(require_string(table, name, "regex"));
+        UrlSource::Regex(try!(Regex::new(&regex).map_err(|e| format!("engines.{}.regex: {}", name, e))))
+      }
+      Some("location") => UrlSource::Location,
+      Some(u) => {
+        return Err(format!("engines.{}.url_from must be \"body\", \"json\", \"regex\" or \"location\", not \"{}\"",
+                           name,
+                           u)
+          .into())
+      }
+    };
+    let private = match table.lookup("private") {
+      Some(p) => some_or_err!(p.as_bool(), format!("engines.{}.private was not a boolean", name).into()),
+      None => false,
+    };
+    Ok(CustomEngine {
+      name: name.to_owned(),
+      domain: domain,
+      upload_url: upload_url,
+      method: method,
+      headers: headers,
+      body: body,
+      url_source: url_source,
+      paste_url: try!(lookup_string(table, name, "paste_url")),
+      raw_url: try!(lookup_string(table, name, "raw_url")).unwrap_or_else(|| String::from("{url}")),
+      private: private
+    })
+  }
+
+  /// Builds the request headers. Configured headers override the default content type of form bodies.
+  fn build_headers(&self) -> Headers {
+    let mut headers = Headers::new();
+    if let BodyMode::Form(_) = self.body {
+      headers.set(ContentType::form_url_encoded());
+    }
+    for &(ref header, ref value) in &self.headers {
+      headers.set_raw(header.clone(), vec![value.as_bytes().to_vec()]);
+    }
+    headers
+  }
+
+  fn format_raw_url(&self, url: &Url) -> String {
+    let id = url.path_segments().and_then(|s| s.filter(|s| !s.is_empty()).last()).unwrap_or("");
+    self.raw_url
+      .replace("{url}", url.as_str())
+      .replace("{path}", url.path())
+      .replace("{id}", id)
+  }
+
+  /// Downloads a paste without following it if it's an index.
+  fn download_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw_url = try!(self.get_raw_url(url));
+    let download = IndexedDownload {
+      url: String::from(raw_url.as_str()),
+      headers: Headers::new(),
+      target: None
+    };
+    Ok(RawPaste {
+      url: raw_url.as_str().to_owned(),
+      file: None,
+      content: try!(download.download(bins))
+    })
+  }
+}
+
+impl UploadsIndices for CustomEngine {
+  fn real_upload(&self, bins: &Bins, data: &PasteFile) -> Result<String> {
+    let body = match self.body {
+      BodyMode::Raw => data.data.clone(),
+      BodyMode::Form(ref field) => {
+        form_urlencoded::Serializer::new(String::new())
+          .append_pair(field, &data.text())
+          .finish()
+          .into_bytes()
+      }
+    };
+    let follow_redirects = match self.url_source {
+      UrlSource::Location => false,
+      _ => true,
+    };
+    let client = try!(bins.network.get_client_with_redirects(&self.upload_url, follow_redirects));
+    let idempotent = self.method.idempotent();
+    let headers = self.build_headers();
+    let mut res = try!(RetryPolicy::new(&bins.config)
+      .send(idempotent, || {
+        client.request(self.method.clone(), &self.upload_url)
+          .headers(headers.clone())
+          .body(&body[..])
+          .send()
+      })
+      .map_err(|e| e.to_string()));
+    let mut s = String::from("");
+    try!(res.read_to_string(&mut s).map_err(|e| e.to_string()));
+    if !res.status.is_success() && !res.status.is_redirection() {
+      return Err(format!("paste could not be created: {}", s.trim()).into());
+    }
+    let value = match self.url_source {
+      UrlSource::Body => s.trim().to_owned(),
+      UrlSource::JsonPointer(ref pointer) => {
+        let json = try!(Json::from_str(&s).map_err(|e| e.to_string()));
+        let found = some_or_err!(find_pointer(&json, pointer),
+                                 format!("response had nothing at {}", pointer).into());
+        match *found {
+          Json::String(ref s) => s.clone(),
+          ref j => j.to_string(),
+        }
+      }
+      UrlSource::Regex(ref regex) => {
+        let captures = some_or_err!(regex.captures(&s), "response did not match regex".into());
+        some_or_err!(captures.at(1).or_else(|| captures.at(0)), "response did not match regex".into()).to_owned()
+      }
+      UrlSource::Location => {
+        let location = some_or_err!(res.headers.get::<Location>(), "response had no location header".into());
+        try!(res.url.join(location).map_err(|e| e.to_string())).as_str().to_owned()
+      }
+    };
+    match self.paste_url {
+      Some(ref template) => Ok(template.replace("{value}", &value)),
+      None => Ok(value),
+    }
+  }
+}
+
+impl ChecksIndices for CustomEngine {}
+
+impl Engine for CustomEngine {
+  fn get_name(&self) -> &str {
+    &self.name
+  }
+
+  fn supports_binary(&self) -> bool {
+    match self.body {
+      BodyMode::Raw => true,
+      BodyMode::Form(_) => false,
+    }
+  }
+
+  fn get_domain(&self) -> &str {
+    &self.domain
+  }
+
+  fn supports_private(&self) -> bool {
+    self.private
+  }
+
+  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<Paste> {
+    UploadsIndices::upload(self, bins, data)
+  }
+
+  fn get_raw_url(&self, url: &Url) -> Result<Url> {
+    let raw_url = self.format_raw_url(url);
+    Url::parse(&raw_url).map_err(|e| format!("invalid raw url {}: {}", raw_url, e).into())
+  }
+
+  fn get_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index(bins, raw, |u| self.get_raw(bins, u))
+  }
+
+  fn get_all_raw(&self, bins: &Bins, url: &Url) -> Result<Vec<RawPaste>> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index_to_all(bins, raw, |u| self.download_raw(bins, u))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{CustomEngine, find_pointer};
+  use hyper::Url;
+  use rustc_serialize::json::Json;
+  use toml::Value;
+
+  fn json() -> Json {
+    Json::from_str(r#"{"data": {"links": [{"url": "https://a.example/1"}, {"url": "https://a.example/2"}]},
+                      "a/b": 1, "m~n": 2, "": 3, "id": 42}"#)
+      .unwrap()
+  }
+
+  fn engine(config: &str) -> CustomEngine {
+    CustomEngine::from_table("test", &config.parse::<Value>().unwrap()).unwrap()
+  }
+
+  #[test]
+  fn find_pointer_in_objects_and_arrays() {
+    let json = json();
+    assert_eq!(find_pointer(&json, "/data/links/1/url"), Some(&Json::String(String::from("https://a.example/2"))));
+    assert_eq!(find_pointer(&json, "/data/links/0"),
+               Some(&Json::from_str(r#"{"url": "https://a.example/1"}"#).unwrap()));
+    assert_eq!(find_pointer(&json, "/id"), Some(&Json::U64(42)));
+    assert_eq!(find_pointer(&json, ""), Some(&json));
+  }
+
+  #[test]
+  fn find_pointer_with_escapes() {
+    let json = json();
+    assert_eq!(find_pointer(&json, "/a~1b"), Some(&Json::U64(1)));
+    assert_eq!(find_pointer(&json, "/m~0n"), Some(&Json::U64(2)));
+    assert_eq!(find_pointer(&json, "/"), Some(&Json::U64(3)));
+  }
+
+  #[test]
+  fn find_pointer_missing() {
+    let json = json();
+    for pointer in &["/nope", "/data/nope", "/data/links/2", "/data/links/-1", "/data/links/url", "/id/0",
+                     "/data/links/0/url/x", "data/links"] {
+      assert_eq!(find_pointer(&json, pointer), None);
+    }
+  }
+
+  #[test]
+  fn format_raw_url_with_id() {
+    let custom = engine("upload_url = \"https://paste.example/api\"\nraw_url = \"https://paste.example/raw/{id}\"");
+    let urls = ["https://paste.example/abc123", "https://paste.example/p/abc123/", "https://paste.example/abc123?x=1"];
+    for url in &urls {
+      assert_eq!(custom.format_raw_url(&Url::parse(url).unwrap()), "https://paste.example/raw/abc123");
+    }
+  }
+
+  #[test]
+  fn format_raw_url_with_url_and_path() {
+    let url = Url::parse("https://paste.example/p/abc123").unwrap();
+    let custom = engine("upload_url = \"https://paste.example/api\"\nraw_url = \"https://raw.example{path}\"");
+    assert_eq!(custom.format_raw_url(&url), "https://raw.example/p/abc123");
+    let custom = engine("upload_url = \"https://paste.example/api\"");
+    assert_eq!(custom.format_raw_url(&url), "https://paste.example/p/abc123");
+  }
+}
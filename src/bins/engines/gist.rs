@@ -1,16 +1,45 @@
 use bins::error::*;
-use bins::{Bins, PasteFile};
+use bins::{Bins, Paste, PastedFile, PasteFile, RawPaste};
 use bins::engines::Engine;
-use bins::engines::indexed::{IndexedDownload, DownloadsFile};
+use bins::engines::indexed::{self, IndexedDownload, DownloadsFile};
 use bins::configuration::BetterLookups;
-use hyper::client::Client;
+use bins::engines::lookup_url;
+use bins::network::RetryPolicy;
+use toml::Value;
+use std::path::Path;
 use hyper::header::{Headers, ContentType, UserAgent, Authorization, Basic};
+use hyper::method::Method;
 use hyper::status::StatusCode;
 use std::collections::BTreeMap;
 use std::io::Read;
-use rustc_serialize::json::{self, Json};
+use rustc_serialize::json::{self, Json, ToJson};
 use hyper::Url;
 
+/// Gist doesn't allow slashes in file names, so paths are stored with `%` as `%25` and `/` as `%2F`.
+fn encode_name(name: &str) -> String {
+  name.replace('%', "%25").replace('/', "%2F")
+}
+
+fn decode_name(name: &str) -> String {
+  let mut decoded = String::with_capacity(name.len());
+  let mut rest = name;
+  while let Some(i) = rest.find('%') {
+    decoded.push_str(&rest[..i]);
+    let escape = &rest[i..];
+    let (c, length) = if escape.starts_with("%2F") || escape.starts_with("%2f") {
+      ('/', 3)
+    } else if escape.starts_with("%25") {
+      ('%', 3)
+    } else {
+      ('%', 1)
+    };
+    decoded.push(c);
+    rest = &escape[length..];
+  }
+  decoded.push_str(rest);
+  decoded
+}
+
 #[derive(RustcEncodable, RustcDecodable)]
 struct GistUpload {
   files: BTreeMap<String, GistFile>,
@@ -29,21 +58,30 @@ impl GistUpload {
   }
 
   fn from(bins: &Bins, files: &[PasteFile]) -> Self {
-    let mut gist = GistUpload::new(None, !bins.arguments.private);
+    let mut gist = GistUpload::new(bins.arguments.title.clone(), !bins.arguments.private);
     for file in files {
-      gist.files.insert(file.name.clone(), GistFile::from(file.data.clone()));
+      // gist detects the language from the file extension
+      let name = match file.language {
+        Some(language) if Path::new(&file.name).extension().is_none() => {
+          format!("{}.{}", file.name, language.get_extension())
+        }
+        _ => file.name.clone(),
+      };
+      gist.files.insert(encode_name(&name), GistFile::from(file.text().into_owned()));
     }
     gist
   }
 
-  fn get_url(&self, bins: &Bins, nth: Option<usize>) -> Result<String> {
+  /// Finds the file to download, returning its name and raw url.
+  fn get_url(&self, bins: &Bins, nth: Option<usize>) -> Result<(String, String)> {
     let target_file = bins.arguments.files.get(0);
-    let files: BTreeMap<String, &GistFile> = self.files.iter().map(|(k, v)| (k.to_lowercase(), v)).collect();
+    let files: BTreeMap<String, (String, &GistFile)> =
+      self.files.iter().map(|(k, v)| (decode_name(k).to_lowercase(), (decode_name(k), v))).collect();
     if files.len() < 1 {
       return Err("gist had no files".into());
     }
     if files.len() > 1 && target_file.is_none() && nth.is_none() {
-      let file_names = self.files.iter().map(|(s, _)| String::from("  ") + s).collect::<Vec<_>>().join("\n");
+      let file_names = files.values().map(|&(ref s, _)| String::from("  ") + s).collect::<Vec<_>>().join("\n");
       let message = format!("gist had more than one file, but no target file was specified\n\nfiles available:\n{}",
                             file_names);
       return Err(message.into());
@@ -62,10 +100,10 @@ impl GistUpload {
     if !files.contains_key(&target) {
       return Err("gist did not contain file".into());
     }
-    let file = &some_or_err!(files.get(&target), "gist did not contain file".into());
+    let &(ref name, file) = some_or_err!(files.get(&target), "gist did not contain file".into());
     let option_raw_url = &file.raw_url;
     let raw_url = some_ref_or_err!(option_raw_url, "file had no raw_url".into());
-    Ok(raw_url.to_owned())
+    Ok((name.clone(), raw_url.to_owned()))
   }
 }
 
@@ -75,6 +113,14 @@ struct GistFile {
   raw_url: Option<String>
 }
 
+impl ToJson for GistFile {
+  fn to_json(&self) -> Json {
+    let mut map = BTreeMap::new();
+    map.insert(String::from("content"), self.content.to_json());
+    Json::Object(map)
+  }
+}
+
 impl From<String> for GistFile {
   fn from(string: String) -> Self {
     GistFile {
@@ -84,15 +130,107 @@ impl From<String> for GistFile {
   }
 }
 
-pub struct Gist;
+pub struct Gist {
+  domain: String,
+  api_url: String
+}
 
 impl Gist {
-  pub fn new() -> Self {
-    Gist {}
+  pub fn new(config: &Value) -> Result<Self> {
+    let (_, domain) = try!(lookup_url(config, "gist.base_url", "https://gist.github.com"));
+    let (api_url, _) = try!(lookup_url(config, "gist.api_url", "https://api.github.com"));
+    Ok(Gist {
+      domain: domain,
+      api_url: api_url
+    })
   }
-}
 
-unsafe impl Sync for Gist {}
+  fn get_auth(bins: &Bins) -> Option<Authorization<Basic>> {
+    let username = match bins.config.lookup_str("gist.username") {
+      Some(u) if !u.is_empty() => u,
+      _ => return None,
+    };
+    let token = match bins.config.lookup_str("gist.access_token") {
+      Some(t) if !t.is_empty() => t,
+      _ => return None,
+    };
+    Some(Authorization(Basic {
+      username: username.to_owned(),
+      password: Some(token.to_owned())
+    }))
+  }
+
+  fn get_html_url(response: &str) -> Result<String> {
+    let raw_gist = try!(Json::from_str(response).map_err(|e| e.to_string()));
+    let gist = some_or_err!(raw_gist.as_object(),
+                            "response was not a json object".into());
+    let html_url = some_or_err!(gist.get("html_url"), "no html_url_key".into());
+    let url = some_or_err!(html_url.as_string(), "html_url was not a string".into());
+    Ok(url.to_owned())
+  }
+
+  fn get_paste(response: &str) -> Result<Paste> {
+    let url = try!(Gist::get_html_url(response));
+    let raw_gist = try!(Json::from_str(response).map_err(|e| e.to_string()));
+    let files = some_or_err!(raw_gist.find("files").and_then(|f| f.as_object()),
+                             "response had no files".into());
+    let mut pasted_files = Vec::new();
+    for (name, file) in files {
+      let raw_url = some_or_err!(file.find("raw_url").and_then(|u| u.as_string()),
+                                 "file had no raw_url".into());
+      pasted_files.push(PastedFile {
+        name: decode_name(name),
+        url: raw_url.to_owned(),
+        size: file.find("size").and_then(|s| s.as_u64()).unwrap_or(0) as usize
+      });
+    }
+    let raw_url = if pasted_files.len() == 1 {
+      Some(pasted_files[0].url.clone())
+    } else {
+      None
+    };
+    Ok(Paste {
+      url: url,
+      raw_url: raw_url,
+      files: pasted_files,
+      delete_key: None
+    })
+  }
+
+  fn get_gist(&self, bins: &Bins, url: &Url) -> Result<GistUpload> {
+    let id = try!(Gist::get_id(url));
+    let url = format!("{}/gists/{}", self.api_url, id);
+    let client = try!(bins.network.get_client(&url));
+    let mut res = try!(RetryPolicy::new(&bins.config)
+      .send(true, || client.get(&url).header(UserAgent(String::from("bins"))).send())
+      .map_err(|e| e.to_string()));
+    let mut s = String::from("");
+    try!(res.read_to_string(&mut s).map_err(|e| e.to_string()));
+    if res.status != StatusCode::Ok {
+      return Err(format!("status was not ok: {}", s.trim()).into());
+    }
+    Ok(try!(json::decode(&s)))
+  }
+
+  fn download_file(bins: &Bins, name: String, raw_url: String) -> Result<RawPaste> {
+    let download = IndexedDownload {
+      url: raw_url.clone(),
+      headers: Headers::new(),
+      target: None
+    };
+    Ok(RawPaste {
+      url: raw_url,
+      file: Some(name),
+      content: try!(download.download(bins))
+    })
+  }
+
+  fn get_id(url: &Url) -> Result<String> {
+    let id = some_or_err!(some_or_err!(url.path_segments(), "could not get path of url".into()).last(),
+                          "could not get last path of url".into());
+    Ok(id.to_owned())
+  }
+}
 
 impl Engine for Gist {
   fn get_name(&self) -> &str {
@@ -100,71 +238,144 @@ impl Engine for Gist {
   }
 
   fn get_domain(&self) -> &str {
-    "gist.github.com"
+    &self.domain
+  }
+
+  fn supports_private(&self) -> bool {
+    true
   }
 
-  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<String> {
+  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<Paste> {
     let upload = GistUpload::from(bins, data);
     let j = try!(json::encode(&upload).map_err(|e| e.to_string()));
-    let client = Client::new();
-    let mut res = try!({
-      let mut builder = client.post("https://api.github.com/gists")
-        .body(&j)
-        .header(ContentType::json())
-        .header(UserAgent(String::from("bins")));
-      if bins.arguments.auth {
-        if let Some(username) = bins.config.lookup_str("gist.username") {
-          if let Some(token) = bins.config.lookup_str("gist.access_token") {
-            if !username.is_empty() && !token.is_empty() {
-              builder = builder.header(Authorization(Basic {
-                username: username.to_owned(),
-                password: Some(token.to_owned())
-              }));
-            }
-          }
+    let auth = if bins.arguments.auth {
+      Gist::get_auth(bins)
+    } else {
+      None
+    };
+    let url = format!("{}/gists", self.api_url);
+    let client = try!(bins.network.get_client(&url));
+    let mut res = try!(RetryPolicy::new(&bins.config)
+      .send(false, || {
+        let mut builder = client.post(&url)
+          .body(&j)
+          .header(ContentType::json())
+          .header(UserAgent(String::from("bins")));
+        if let Some(ref auth) = auth {
+          builder = builder.header(auth.clone());
         }
-      }
-      builder.send()
-        .map_err(|e| e.to_string())
-    });
+        builder.send()
+      })
+      .map_err(|e| e.to_string()));
     let mut s = String::from("");
     try!(res.read_to_string(&mut s).map_err(|e| e.to_string()));
     if res.status != StatusCode::Created {
-      println!("{}", s);
-      return Err("paste could not be created".into());
+      return Err(format!("paste could not be created: {}", s.trim()).into());
     }
-    let raw_gist = try!(Json::from_str(&s).map_err(|e| e.to_string()));
-    let gist = some_or_err!(raw_gist.as_object(),
-                            "response was not a json object".into());
-    let html_url = some_or_err!(gist.get("html_url"), "no html_url_key".into());
-    let url = some_or_err!(html_url.as_string(), "html_url was not a string".into());
-    Ok(url.to_owned())
+    Gist::get_paste(&s)
   }
 
-  fn get_raw(&self, bins: &Bins, url: &mut Url) -> Result<String> {
-    let id = some_or_err!(some_or_err!(url.path_segments(), "could not get path of url".into()).last(),
-                          "could not get last path of url".into());
+  fn get_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
     if bins.arguments.files.len() > 1 {
-      return Err("currently, only one file is able to be retrieved in input mode".into());
+      return Err("only one file can be retrieved in input mode (use --output-dir to download every file)".into());
+    }
+    let gist = try!(self.get_gist(bins, url));
+    let (name, raw_url) = try!(gist.get_url(bins, bins.arguments.nth));
+    Gist::download_file(bins, name, raw_url)
+  }
+
+  fn get_all_raw(&self, bins: &Bins, url: &Url) -> Result<Vec<RawPaste>> {
+    let gist = try!(self.get_gist(bins, url));
+    let mut files = Vec::new();
+    for (name, file) in gist.files {
+      let raw_url = some_or_err!(file.raw_url, "file had no raw_url".into());
+      files.push((decode_name(&name), raw_url));
+    }
+    indexed::parallel(bins, &files, |&(ref name, ref raw_url)| Gist::download_file(bins, name.clone(), raw_url.clone()))
+  }
+
+  fn delete(&self, bins: &Bins, url: &Url) -> Result<()> {
+    let id = try!(Gist::get_id(url));
+    let auth = some_or_err!(Gist::get_auth(bins),
+                            "deleting a gist requires gist.username and gist.access_token in configuration file".into());
+    let url = format!("{}/gists/{}", self.api_url, id);
+    let client = try!(bins.network.get_client(&url));
+    let mut res = try!(RetryPolicy::new(&bins.config)
+      .send(true, || {
+        client.delete(&url)
+          .header(UserAgent(String::from("bins")))
+          .header(auth.clone())
+          .send()
+      })
+      .map_err(|e| e.to_string()));
+    if res.status != StatusCode::NoContent {
+      let mut s = String::from("");
+      try!(res.read_to_string(&mut s).map_err(|e| e.to_string()));
+      return Err(format!("paste could not be deleted: {}", s.trim()).into());
+    }
+    Ok(())
+  }
+
+  fn update(&self, bins: &Bins, url: &Url, data: &[PasteFile]) -> Result<String> {
+    let id = try!(Gist::get_id(url));
+    if !bins.arguments.auth {
+      return Err("updating a gist requires authentication, but anonymous mode was specified".into());
     }
-    let client = Client::new();
-    let mut res = try!(client.get(&format!("https://api.github.com/gists/{}", id))
-      .header(UserAgent(String::from("bins")))
-      .send()
+    let auth = some_or_err!(Gist::get_auth(bins),
+                            "updating a gist requires gist.username and gist.access_token in configuration file".into());
+    let upload = GistUpload::from(bins, data);
+    let mut files: BTreeMap<String, Json> = upload.files.iter().map(|(k, v)| (k.clone(), v.to_json())).collect();
+    // setting a file to null removes it from the gist
+    for name in &bins.arguments.remove {
+      files.insert(encode_name(name), Json::Null);
+    }
+    let mut object = BTreeMap::new();
+    object.insert(String::from("files"), Json::Object(files));
+    if let Some(ref title) = bins.arguments.title {
+      object.insert(String::from("description"), title.to_json());
+    }
+    let j = Json::Object(object).to_string();
+    let url = format!("{}/gists/{}", self.api_url, id);
+    let client = try!(bins.network.get_client(&url));
+    // sending the same files again leaves the gist in the same state, so updates are safe to retry
+    let mut res = try!(RetryPolicy::new(&bins.config)
+      .send(true, || {
+        client.request(Method::Patch, &url)
+          .body(&j)
+          .header(ContentType::json())
+          .header(UserAgent(String::from("bins")))
+          .header(auth.clone())
+          .send()
+      })
       .map_err(|e| e.to_string()));
     let mut s = String::from("");
     try!(res.read_to_string(&mut s).map_err(|e| e.to_string()));
     if res.status != StatusCode::Ok {
-      println!("{}", s);
-      return Err("status was not ok".into());
+      return Err(format!("paste could not be updated: {}", s.trim()).into());
     }
-    let gist_upload: GistUpload = try!(json::decode(&s));
-    let raw_url = try!(gist_upload.get_url(bins, bins.arguments.nth));
-    let download = IndexedDownload {
-      url: raw_url,
-      headers: Headers::new(),
-      target: None
-    };
-    download.download()
+    Gist::get_html_url(&s)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{decode_name, encode_name};
+
+  #[test]
+  fn encode_decode_round_trip() {
+    let names = ["plain.txt", "src/main.rs", "/", "100%", "%", "%2F", "%25", "%2f", "a%2Fb/c%25d", "%%2F%",
+                 "\u{65e5}\u{672c}/%"];
+    for name in &names {
+      let encoded = encode_name(name);
+      assert!(!encoded.contains('/'), "{} was encoded as {}", name, encoded);
+      assert_eq!(decode_name(&encoded), *name);
+    }
+  }
+
+  #[test]
+  fn decode_unescaped_names() {
+    assert_eq!(decode_name("50% off.txt"), "50% off.txt");
+    assert_eq!(decode_name("src%2fmain.rs"), "src/main.rs");
+    assert_eq!(decode_name("trailing%"), "trailing%");
   }
 }
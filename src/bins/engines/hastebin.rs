@@ -1,36 +1,54 @@
 use bins::error::*;
-use bins::{Bins, PasteFile};
+use bins::{Bins, Paste, PasteFile, RawPaste};
 use bins::engines::Engine;
 use hyper::client::Response;
 use rustc_serialize::json::Json;
 use bins::engines::indexed::{IndexedUpload, UploadsIndices, ProducesUrl, ProducesBody};
 use bins::engines::indexed::{ChecksIndices, IndexedDownload, DownloadsFile};
+use bins::engines::lookup_url;
 use hyper::header::Headers;
 use hyper::Url;
+use toml::Value;
 
 pub struct Hastebin {
+  domain: String,
   indexed_upload: IndexedUpload
 }
 
 impl Hastebin {
-  pub fn new() -> Self {
-    Hastebin {
+  pub fn new(config: &Value) -> Result<Self> {
+    let (base_url, domain) = try!(lookup_url(config, "hastebin.base_url", "http://hastebin.com"));
+    Ok(Hastebin {
+      domain: domain,
       indexed_upload: IndexedUpload {
-        url: String::from("http://hastebin.com/documents"),
-        headers: Headers::new(),
+        url: base_url + "/documents",
+        headers: Vec::new(),
         url_producer: Box::new(HastebinUrlProducer {}),
         body_producer: Box::new(HastebinBodyProducer {})
       }
-    }
+    })
   }
-}
 
-unsafe impl Sync for Hastebin {}
+  /// Downloads a paste without following it if it's an index.
+  fn download_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw_url = try!(self.get_raw_url(url));
+    let download = IndexedDownload {
+      url: String::from(raw_url.as_str()),
+      headers: Headers::new(),
+      target: None
+    };
+    Ok(RawPaste {
+      url: raw_url.as_str().to_owned(),
+      file: None,
+      content: try!(download.download(bins))
+    })
+  }
+}
 
 struct HastebinUrlProducer { }
 
 impl ProducesUrl for HastebinUrlProducer {
-  fn produce_url(&self, _: &Bins, res: Response, data: String) -> Result<String> {
+  fn produce_url(&self, _: &Bins, file: &PasteFile, res: Response, data: String) -> Result<String> {
     let raw_response = try!(Json::from_str(&data).map_err(|e| e.to_string()));
     let response = some_or_err!(raw_response.as_object(),
                                 "response was not a json object".into());
@@ -38,7 +56,8 @@ impl ProducesUrl for HastebinUrlProducer {
     let key = some_or_err!(raw_key.as_string(), "key was not a string".into());
     let scheme = res.url.scheme();
     let host = some_or_err!(res.url.host_str(), "no host string".into());
-    Ok(format!("{}://{}/{}", scheme, host, key))
+    let extension = file.language.map_or(String::new(), |l| String::from(".") + l.get_extension());
+    Ok(format!("{}://{}/{}{}", scheme, host, key, extension))
   }
 }
 
@@ -46,7 +65,7 @@ struct HastebinBodyProducer { }
 
 impl ProducesBody for HastebinBodyProducer {
   fn produce_body(&self, _: &Bins, data: &PasteFile) -> Result<String> {
-    Ok(data.clone().data)
+    Ok(data.text().into_owned())
   }
 }
 
@@ -58,32 +77,29 @@ impl Engine for Hastebin {
   }
 
   fn get_domain(&self) -> &str {
-    "hastebin.com"
+    &self.domain
   }
 
-  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<String> {
+  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<Paste> {
     self.indexed_upload.upload(bins, data)
   }
 
-  fn get_raw(&self, bins: &Bins, url: &mut Url) -> Result<String> {
+  fn get_raw_url(&self, url: &Url) -> Result<Url> {
     let new_path = {
       String::from("/raw") + url.path().split('.').collect::<Vec<_>>()[0]
     };
-    url.set_path(new_path.as_ref());
-    let download = IndexedDownload {
-      url: String::from(url.as_str()),
-      headers: Headers::new(),
-      target: None
-    };
-    let downloaded = try!(download.download());
-    match self.check_index(bins, &downloaded) {
-      Ok(mut new_url) => return self.get_raw(bins, &mut new_url),
-      Err(e) => {
-        if let ErrorKind::InvalidIndexError = *e.kind() {} else {
-          return Err(e);
-        }
-      }
-    }
-    Ok(downloaded)
+    let mut raw_url = url.clone();
+    raw_url.set_path(new_path.as_ref());
+    Ok(raw_url)
+  }
+
+  fn get_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index(bins, raw, |u| self.get_raw(bins, u))
+  }
+
+  fn get_all_raw(&self, bins: &Bins, url: &Url) -> Result<Vec<RawPaste>> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index_to_all(bins, raw, |u| self.download_raw(bins, u))
   }
 }
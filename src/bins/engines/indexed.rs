@@ -1,7 +1,14 @@
 use bins::error::*;
-use bins::{Bins, PasteFile};
+use bins::{Bins, Paste, PastedFile, PasteFile, RawPaste};
+use bins::binary;
+use bins::configuration::BetterLookups;
+use bins::network::RetryPolicy;
+use crossbeam;
+use std::cmp;
 use std::iter::repeat;
-use hyper::client::Client;
+use std::str;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use hyper::client::Response;
 use hyper::header::Headers;
 use hyper::Url;
@@ -42,56 +49,128 @@ impl Index {
   }
 }
 
+/// Runs `f` on every item with up to `defaults.concurrency` threads, returning the results in the same order as the
+/// items. No new items are started once one has failed.
+pub fn parallel<T, R, F>(bins: &Bins, items: &[T], f: F) -> Result<Vec<R>>
+  where T: Sync,
+        R: Send,
+        F: Fn(&T) -> Result<R> + Sync
+{
+  let concurrency = cmp::min(cmp::max(bins.config.lookup_int_or("defaults.concurrency", 4), 1) as usize,
+                             items.len());
+  let next = AtomicUsize::new(0);
+  let failed = AtomicBool::new(false);
+  let results: Mutex<Vec<Option<::std::result::Result<R, String>>>> =
+    Mutex::new((0..items.len()).map(|_| None).collect());
+  crossbeam::scope(|scope| {
+    for _ in 0..concurrency {
+      scope.spawn(|| {
+        while !failed.load(Ordering::SeqCst) {
+          let i = next.fetch_add(1, Ordering::SeqCst);
+          if i >= items.len() {
+            break;
+          }
+          let result = f(&items[i]).map_err(|e| e.iter().map(|x| x.to_string()).collect::<Vec<_>>().join("\n"));
+          if result.is_err() {
+            failed.store(true, Ordering::SeqCst);
+          }
+          results.lock().expect("worker thread panicked")[i] = Some(result);
+        }
+      });
+    }
+  });
+  let mut outputs = Vec::with_capacity(items.len());
+  // items are handed out in order, so any item that was never started comes after the failure
+  for result in results.into_inner().expect("worker thread panicked") {
+    match result {
+      Some(Ok(output)) => outputs.push(output),
+      Some(Err(e)) => return Err(e.into()),
+      None => return Err("not all files were transferred".into()),
+    }
+  }
+  Ok(outputs)
+}
+
 pub struct IndexedUpload {
   pub url: String,
-  pub headers: Headers,
-  pub url_producer: Box<ProducesUrl>,
-  pub body_producer: Box<ProducesBody>
+  /// Raw header names and values, which unlike `Headers` can be shared between upload threads.
+  pub headers: Vec<(String, String)>,
+  pub url_producer: Box<ProducesUrl + Sync>,
+  pub body_producer: Box<ProducesBody + Sync>
+}
+
+impl IndexedUpload {
+  /// Builds the headers for a request from the raw header pairs.
+  pub fn hyper_headers(&self) -> Headers {
+    let mut headers = Headers::new();
+    for &(ref name, ref value) in &self.headers {
+      headers.set_raw(name.clone(), vec![value.as_bytes().to_vec()]);
+    }
+    headers
+  }
 }
 
-pub trait ProducesUrl {
-  fn produce_url(&self, bins: &Bins, res: Response, data: String) -> Result<String>;
+pub trait ProducesUrl: Sync {
+  fn produce_url(&self, bins: &Bins, file: &PasteFile, res: Response, data: String) -> Result<String>;
 }
 
-pub trait ProducesBody {
+pub trait ProducesBody: Sync {
   fn produce_body(&self, bins: &Bins, data: &PasteFile) -> Result<String>;
 }
 
-pub trait UploadsIndices {
+pub trait UploadsIndices: Sync {
   fn real_upload(&self, bins: &Bins, data: &PasteFile) -> Result<String>;
 
-  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<String> {
+  /// Uploads every file with up to `defaults.concurrency` uploads at a time, returning the urls in the same order as
+  /// the files.
+  fn upload_all(&self, bins: &Bins, data: &[PasteFile]) -> Result<Vec<String>> {
+    parallel(bins, data, |file| self.real_upload(bins, file))
+  }
+
+  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<Paste> {
     if data.len() < 2 {
-      return self.real_upload(bins, &data[0]);
-    }
-    let wrapped_urls = data.iter()
-      .map(|f| self.real_upload(bins, f))
-      .map(|r| r.map_err(|e| e.iter().map(|x| x.to_string()).collect::<Vec<_>>().join("\n")))
-      .collect::<Vec<_>>();
-    for url in wrapped_urls.iter().cloned() {
-      if url.is_err() {
-        return Err(url.err().unwrap().into());
+      let mut file = data[0].clone();
+      // the title names the paste the user gets back, which is the only paste here
+      if let Some(ref title) = bins.arguments.title {
+        file.name = title.clone();
       }
+      let url = try!(self.real_upload(bins, &file));
+      return Ok(Paste {
+        url: url.clone(),
+        raw_url: None,
+        files: vec![PastedFile::new(&data[0], url)],
+        delete_key: None
+      });
     }
-    let urls = wrapped_urls.iter().cloned().map(|r| r.unwrap());
-    let mut index = self.generate_index(data);
+    let urls = try!(self.upload_all(bins, data));
+    let mut index = self.generate_index(bins, data);
     let mut number = 1;
-    for url in urls {
+    for url in &urls {
       let replace = String::from("<url") + &number.to_string() + ">";
       index = index.replace(&replace, url.as_ref());
       number += 1;
     }
     let index_url = try!(self.real_upload(bins,
                                           &PasteFile {
-                                            name: String::from("index"),
-                                            data: index
+                                            name: bins.arguments.title.clone().unwrap_or_else(|| String::from("index")),
+                                            data: index.into_bytes(),
+                                            mime: binary::TEXT,
+                                            language: None
                                           }));
-    Ok(index_url)
+    Ok(Paste {
+      url: index_url,
+      raw_url: None,
+      files: data.iter().zip(urls.into_iter()).map(|(f, u)| PastedFile::new(f, u)).collect(),
+      delete_key: None
+    })
   }
 
-  fn generate_index(&self, data: &[PasteFile]) -> String {
-    let header = format!("{} files", data.len());
-    let separator = Self::repeat_str("-", header.len());
+  fn generate_index(&self, bins: &Bins, data: &[PasteFile]) -> String {
+    let header = match bins.arguments.title {
+      Some(ref title) => title.replace('\n', " "),
+      None => format!("{} files", data.len()),
+    };
+    let separator = Self::repeat_str("-", header.chars().count());
     let mut body = String::from("");
     for (i, file) in data.iter().enumerate() {
       let number = i + 1;
@@ -109,20 +188,35 @@ pub trait UploadsIndices {
 
 impl UploadsIndices for IndexedUpload {
   fn real_upload(&self, bins: &Bins, data: &PasteFile) -> Result<String> {
-    let client = Client::new();
-    let mut res = try!(client.post(&self.url)
-      .headers(self.headers.clone())
-      .body(&try!(self.body_producer.as_ref().produce_body(bins, data)))
-      .send()
+    let body = try!(self.body_producer.as_ref().produce_body(bins, data));
+    let client = try!(bins.network.get_client(&self.url));
+    let headers = self.hyper_headers();
+    let mut res = try!(RetryPolicy::new(&bins.config)
+      .send(false, || client.post(&self.url).headers(headers.clone()).body(&body).send())
       .map_err(|e| e.to_string()));
     let mut s = String::from("");
     try!(res.read_to_string(&mut s).map_err(|e| e.to_string()));
     // 404 for pastie, which appears to have issues when redirecting?
     if res.status != StatusCode::Ok && res.status != StatusCode::NotFound {
-      println!("{}", s);
-      return Err("paste could not be created".into());
+      return Err(format!("paste could not be created: {}", s.trim()).into());
+    }
+    self.url_producer.as_ref().produce_url(bins, data, res, s)
+  }
+}
+
+pub trait DeletesIndices {
+  fn real_delete(&self, bins: &Bins, url: &Url) -> Result<()>;
+
+  fn delete_indexed(&self, bins: &Bins, url: &Url, downloaded: &[u8]) -> Result<()> {
+    if let Some(index) = str::from_utf8(downloaded).ok().and_then(|d| Index::from(d).ok()) {
+      let file_urls = index.file_urls.values().map(|u| Url::parse(u.as_ref())).collect::<Vec<_>>();
+      if file_urls.iter().all(|u| u.is_ok()) {
+        for file_url in file_urls.into_iter().map(|u| u.unwrap()) {
+          try!(self.real_delete(bins, &file_url));
+        }
+      }
     }
-    self.url_producer.as_ref().produce_url(bins, res, s)
+    self.real_delete(bins, url)
   }
 }
 
@@ -133,25 +227,28 @@ pub struct IndexedDownload {
 }
 
 pub trait DownloadsFile {
-  fn download(&self) -> Result<String>;
+  fn download(&self, bins: &Bins) -> Result<Vec<u8>>;
 }
 
 impl DownloadsFile for IndexedDownload {
-  fn download(&self) -> Result<String> {
-    let client = Client::new();
-    let mut res = try!(client.get(&self.url).headers(self.headers.clone()).send());
+  fn download(&self, bins: &Bins) -> Result<Vec<u8>> {
+    let client = try!(bins.network.get_client(&self.url));
+    let mut res = try!(RetryPolicy::new(&bins.config)
+      .send(true, || client.get(&self.url).headers(self.headers.clone()).send()));
     if res.status != StatusCode::Ok {
       return Err(format!("status was not ok: {}", res.status).into());
     }
-    let mut s = String::from("");
-    try!(res.read_to_string(&mut s));
-    Ok(s)
+    let mut data = Vec::new();
+    try!(res.read_to_end(&mut data));
+    Ok(data)
   }
 }
 
 pub trait ChecksIndices {
-  fn get_url_from_index(&self, bins: &Bins, index: &Index, nth: Option<usize>) -> Result<Url> {
-    let urls: LinkedHashMap<String, &String> = index.file_urls.iter().map(|(k, v)| (k.to_lowercase(), v)).collect();
+  /// Finds the file to download from an index, returning its name and url.
+  fn get_url_from_index(&self, bins: &Bins, index: &Index, nth: Option<usize>) -> Result<(String, Url)> {
+    let urls: LinkedHashMap<String, (&String, &String)> =
+      index.file_urls.iter().map(|(k, v)| (k.to_lowercase(), (k, v))).collect();
     if urls.len() < 1 {
       return Err("index had no files".into());
     }
@@ -177,16 +274,56 @@ pub trait ChecksIndices {
     if !urls.contains_key(&target) {
       return Err("index did not contain file".into());
     }
-    match Url::parse(urls[&target].as_ref()) {
-      Ok(u) => return Ok(u),
+    let (name, url) = urls[&target];
+    match Url::parse(url.as_ref()) {
+      Ok(u) => return Ok((name.clone(), u)),
       Err(e) => return Err(e.to_string().into()),
     }
   }
 
-  fn check_index(&self, bins: &Bins, downloaded: &str) -> Result<Url> {
-    if let Ok(index) = Index::from(downloaded) {
+  fn check_index(&self, bins: &Bins, downloaded: &[u8]) -> Result<(String, Url)> {
+    if let Some(index) = str::from_utf8(downloaded).ok().and_then(|d| Index::from(d).ok()) {
       return self.get_url_from_index(bins, &index, bins.arguments.nth);
     }
     Err(ErrorKind::InvalidIndexError.into())
   }
+
+  /// Follows an index to the file chosen on the command line, or returns `raw` itself if it isn't an index.
+  fn follow_index<F>(&self, bins: &Bins, raw: RawPaste, get_raw: F) -> Result<RawPaste>
+    where F: Fn(&Url) -> Result<RawPaste>
+  {
+    let checked = self.check_index(bins, &raw.content);
+    match checked {
+      Ok((name, url)) => {
+        let mut file = try!(get_raw(&url));
+        file.file = Some(name);
+        Ok(file)
+      }
+      Err(e) => {
+        if let ErrorKind::InvalidIndexError = *e.kind() {
+          Ok(raw)
+        } else {
+          Err(e)
+        }
+      }
+    }
+  }
+
+  /// Downloads every file in an index in parallel, or returns `raw` itself if it isn't an index.
+  fn follow_index_to_all<F>(&self, bins: &Bins, raw: RawPaste, get_raw: F) -> Result<Vec<RawPaste>>
+    where F: Fn(&Url) -> Result<RawPaste> + Sync
+  {
+    let index = str::from_utf8(&raw.content).ok().and_then(|d| Index::from(d).ok());
+    let index = match index {
+      Some(index) => index,
+      None => return Ok(vec![raw]),
+    };
+    let files = index.file_urls.into_iter().collect::<Vec<_>>();
+    parallel(bins, &files, |&(ref name, ref url)| {
+      let url = try!(Url::parse(url).map_err(|e| e.to_string()));
+      let mut file = try!(get_raw(&url));
+      file.file = Some(name.clone());
+      Ok(file)
+    })
+  }
 }
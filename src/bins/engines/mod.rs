@@ -2,36 +2,57 @@ pub mod gist;
 pub mod hastebin;
 pub mod pastie;
 pub mod pastebin;
+pub mod privatebin;
 pub mod sprunge;
+pub mod custom;
 mod indexed;
 
 use bins::error::*;
-use bins::PasteFile;
-use bins::Bins;
+use bins::configuration::BetterLookups;
+use bins::{Bins, Paste, PasteFile, RawPaste};
+use bins::expiration::Expiration;
 use hyper::Url;
+use toml::Value;
 
-lazy_static! {
-  pub static ref ENGINES: Vec<Box<Engine>> = {
-      vec![
-        Box::new(gist::Gist::new()),
-        Box::new(hastebin::Hastebin::new()),
-        Box::new(pastie::Pastie::new()),
-        Box::new(pastebin::Pastebin::new()),
-        Box::new(sprunge::Sprunge::new())
-      ]
+/// Creates the built-in engines, followed by the engines defined in the `engines` section of the configuration.
+pub fn get_engines(config: &Value) -> Result<Vec<Box<Engine>>> {
+  let mut engines: Vec<Box<Engine>> = vec![Box::new(try!(gist::Gist::new(config))),
+                                           Box::new(try!(hastebin::Hastebin::new(config))),
+                                           Box::new(try!(pastie::Pastie::new(config))),
+                                           Box::new(try!(pastebin::Pastebin::new(config))),
+                                           Box::new(try!(privatebin::PrivateBin::new(config))),
+                                           Box::new(try!(sprunge::Sprunge::new(config)))];
+  for engine in try!(custom::CustomEngine::from_config(config)) {
+    if get_engine_by_name(&engines, engine.get_name()).is_some() {
+      return Err(format!("engine \"{}\" is already defined", engine.get_name()).into());
+    }
+    engines.push(Box::new(engine));
+  }
+  Ok(engines)
+}
+
+/// Looks up a url in the configuration, like `hastebin.base_url`, falling back to `default`.
+///
+/// The url is returned without a trailing slash, along with its host.
+fn lookup_url(config: &Value, path: &str, default: &str) -> Result<(String, String)> {
+  let url_string = config.lookup_str_or(path, default).trim_right_matches('/').to_owned();
+  let host = {
+    let url = try!(Url::parse(&url_string).map_err(|e| format!("{} was not a valid url: {}", path, e)));
+    some_or_err!(url.host_str(), format!("{} had no host", path).into()).to_owned()
   };
+  Ok((url_string, host))
 }
 
-pub fn get_engine_names<'a>() -> Vec<&'a str> {
-  ENGINES.iter().map(|e| e.get_name()).collect()
+pub fn get_engine_names(engines: &[Box<Engine>]) -> Vec<&str> {
+  engines.iter().map(|e| e.get_name()).collect()
 }
 
-pub fn get_engine_by_name<'a>(name: &'a str) -> Option<&Box<Engine>> {
-  ENGINES.iter().find(|e| e.get_name().to_lowercase() == name.to_lowercase())
+pub fn get_engine_by_name<'a>(engines: &'a [Box<Engine>], name: &str) -> Option<&'a Box<Engine>> {
+  engines.iter().find(|e| e.get_name().to_lowercase() == name.to_lowercase())
 }
 
-pub fn get_engine_by_domain<'a>(domain: &'a str) -> Option<&Box<Engine>> {
-  ENGINES.iter().find(|e| e.get_domain().to_lowercase() == domain.to_lowercase())
+pub fn get_engine_by_domain<'a>(engines: &'a [Box<Engine>], domain: &str) -> Option<&'a Box<Engine>> {
+  engines.iter().find(|e| e.get_domain().to_lowercase() == domain.to_lowercase())
 }
 
 pub trait Engine: Sync {
@@ -39,7 +60,42 @@ pub trait Engine: Sync {
 
   fn get_domain(&self) -> &str;
 
-  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<String>;
+  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<Paste>;
+
+  fn get_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste>;
+
+  /// Downloads every file in a paste, for `--output-dir`. Pastes with one file are downloaded like in input mode.
+  fn get_all_raw(&self, bins: &Bins, url: &Url) -> Result<Vec<RawPaste>> {
+    self.get_raw(bins, url).map(|raw| vec![raw])
+  }
+
+  /// Whether files can be uploaded as they are, even if they aren't text.
+  fn supports_binary(&self) -> bool {
+    false
+  }
+
+  /// Whether pastes can be kept out of public listings when `--private` is given.
+  fn supports_private(&self) -> bool {
+    false
+  }
+
+  /// Gets the url of the raw contents of a paste.
+  fn get_raw_url(&self, _: &Url) -> Result<Url> {
+    Err(format!("{} does not have raw urls", self.get_name()).into())
+  }
+
+  fn check_expiration(&self, bins: &Bins) -> Result<()> {
+    match bins.arguments.expire {
+      Some(Expiration::After(_)) => Err(format!("{} does not support expiring pastes", self.get_name()).into()),
+      _ => Ok(()),
+    }
+  }
+
+  fn delete(&self, _: &Bins, _: &Url) -> Result<()> {
+    Err(format!("{} does not support deleting pastes", self.get_name()).into())
+  }
 
-  fn get_raw(&self, bins: &Bins, url: &mut Url) -> Result<String>;
+  fn update(&self, _: &Bins, _: &Url, _: &[PasteFile]) -> Result<String> {
+    Err(format!("{} does not support updating pastes", self.get_name()).into())
+  }
 }
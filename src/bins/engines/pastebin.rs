@@ -1,39 +1,101 @@
 use bins::error::*;
-use bins::{Bins, PasteFile};
+use bins::{Bins, Paste, PasteFile, RawPaste};
 use bins::engines::Engine;
 use hyper::client::Response;
 use bins::engines::indexed::{IndexedUpload, UploadsIndices, ProducesUrl, ProducesBody};
-use bins::engines::indexed::{ChecksIndices, IndexedDownload, DownloadsFile};
+use bins::engines::indexed::{ChecksIndices, DeletesIndices, IndexedDownload, DownloadsFile};
 use bins::configuration::BetterLookups;
+use bins::expiration::Expiration;
 use hyper::header::{Headers, ContentType, Referer};
+use hyper::status::StatusCode;
 use hyper::Url;
+use std::io::Read;
+use bins::engines::lookup_url;
+use bins::network::RetryPolicy;
+use toml::Value;
 use url::form_urlencoded;
 
+const EXPIRATIONS: &'static [(u64, &'static str)] = &[(600, "10M"), (3600, "1H"), (86400, "1D"), (604800, "1W"),
+                                                       (1209600, "2W"), (2592000, "1M"), (15552000, "6M"),
+                                                       (31536000, "1Y")];
+
 pub struct Pastebin {
+  domain: String,
   indexed_upload: IndexedUpload
 }
 
-unsafe impl Sync for Pastebin {}
-
 impl Pastebin {
-  pub fn new() -> Self {
-    let mut headers = Headers::new();
-    headers.set(ContentType::form_url_encoded());
-    Pastebin {
+  pub fn new(config: &Value) -> Result<Self> {
+    let (base_url, domain) = try!(lookup_url(config, "pastebin.base_url", "http://pastebin.com"));
+    Ok(Pastebin {
+      domain: domain,
       indexed_upload: IndexedUpload {
-        url: String::from("http://pastebin.com/api/api_post.php"),
-        headers: headers,
+        url: base_url + "/api/api_post.php",
+        headers: vec![(String::from("Content-Type"), ContentType::form_url_encoded().to_string())],
         url_producer: Box::new(PastebinUrlProducer {}),
         body_producer: Box::new(PastebinBodyProducer {})
       }
+    })
+  }
+
+  fn get_api_key(bins: &Bins) -> Result<&str> {
+    let api_key = some_or_err!(bins.config.lookup_str("pastebin.api_key"),
+                               "no pastebin.api_key defined in configuration file".into());
+    if api_key.is_empty() {
+      return Err("pastebin.api_key was empty".into());
     }
+    Ok(api_key)
+  }
+
+  fn get_user_key(bins: &Bins) -> Option<&str> {
+    bins.config.lookup_str("pastebin.api_user_key").and_then(|k| if k.is_empty() { None } else { Some(k) })
+  }
+
+  fn get_expire_date(bins: &Bins) -> Result<&'static str> {
+    match bins.arguments.expire {
+      None | Some(Expiration::Never) => Ok("N"),
+      Some(Expiration::After(seconds)) => {
+        match EXPIRATIONS.iter().find(|e| e.0 == seconds) {
+          Some(e) => Ok(e.1),
+          None => {
+            let supported = EXPIRATIONS.iter().map(|e| Expiration::After(e.0).to_string()).collect::<Vec<_>>();
+            Err(format!("pastebin does not support an expiration of {} (supported: never, {})",
+                        Expiration::After(seconds),
+                        supported.join(", "))
+              .into())
+          }
+        }
+      }
+    }
+  }
+
+  fn download(&self, bins: &Bins, url: &Url) -> Result<Vec<u8>> {
+    let raw_url = try!(self.get_raw_url(url));
+    let mut headers = Headers::new();
+    headers.set(Referer(raw_url.as_str().to_owned()));
+    let download = IndexedDownload {
+      url: String::from(raw_url.as_str()),
+      headers: headers,
+      target: None
+    };
+    download.download(bins)
+  }
+
+  /// Downloads a paste without following it if it's an index.
+  fn download_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw_url = try!(self.get_raw_url(url));
+    Ok(RawPaste {
+      url: raw_url.as_str().to_owned(),
+      file: None,
+      content: try!(self.download(bins, url))
+    })
   }
 }
 
 struct PastebinUrlProducer { }
 
 impl ProducesUrl for PastebinUrlProducer {
-  fn produce_url(&self, _: &Bins, _: Response, data: String) -> Result<String> {
+  fn produce_url(&self, _: &Bins, _: &PasteFile, _: Response, data: String) -> Result<String> {
     Ok(data)
   }
 }
@@ -42,13 +104,9 @@ struct PastebinBodyProducer { }
 
 impl ProducesBody for PastebinBodyProducer {
   fn produce_body(&self, bins: &Bins, data: &PasteFile) -> Result<String> {
-    let api_key = some_or_err!(bins.config.lookup_str("pastebin.api_key"),
-                               "no pastebin.api_key defined in configuration file".into());
-    if api_key.is_empty() {
-      return Err("pastebin.api_key was empty".into());
-    }
-    Ok(form_urlencoded::Serializer::new(String::new())
-      .append_pair("api_option", "paste")
+    let api_key = try!(Pastebin::get_api_key(bins));
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    serializer.append_pair("api_option", "paste")
       .append_pair("api_dev_key", api_key)
       .append_pair("api_paste_private",
                    if bins.arguments.private {
@@ -56,48 +114,94 @@ impl ProducesBody for PastebinBodyProducer {
                    } else {
                      "0"
                    })
-      .append_pair("api_paste_code", &data.data)
-      .append_pair("api_paste_name", &data.name)
-      .finish())
+      .append_pair("api_paste_expire_date", try!(Pastebin::get_expire_date(bins)))
+      .append_pair("api_paste_code", &data.text())
+      .append_pair("api_paste_name", &data.name);
+    if let Some(language) = data.language {
+      serializer.append_pair("api_paste_format", language.pastebin);
+    }
+    if bins.arguments.auth {
+      if let Some(user_key) = Pastebin::get_user_key(bins) {
+        serializer.append_pair("api_user_key", user_key);
+      }
+    }
+    Ok(serializer.finish())
   }
 }
 
 impl ChecksIndices for Pastebin {}
 
+impl DeletesIndices for Pastebin {
+  fn real_delete(&self, bins: &Bins, url: &Url) -> Result<()> {
+    let api_key = try!(Pastebin::get_api_key(bins));
+    let user_key = some_or_err!(Pastebin::get_user_key(bins),
+                                "deleting a paste requires pastebin.api_user_key in configuration file".into());
+    let key = some_or_err!(some_or_err!(url.path_segments(), "could not get path of url".into()).last(),
+                           "could not get last path of url".into());
+    let body = form_urlencoded::Serializer::new(String::new())
+      .append_pair("api_option", "delete")
+      .append_pair("api_dev_key", api_key)
+      .append_pair("api_user_key", user_key)
+      .append_pair("api_paste_key", key)
+      .finish();
+    let client = try!(bins.network.get_client(&self.indexed_upload.url));
+    let headers = self.indexed_upload.hyper_headers();
+    let mut res = try!(RetryPolicy::new(&bins.config)
+      .send(false, || {
+        client.post(&self.indexed_upload.url)
+          .headers(headers.clone())
+          .body(&body)
+          .send()
+      })
+      .map_err(|e| e.to_string()));
+    let mut s = String::from("");
+    try!(res.read_to_string(&mut s).map_err(|e| e.to_string()));
+    if res.status != StatusCode::Ok || s.trim() != "Paste Removed" {
+      return Err(format!("paste could not be deleted: {}", s.trim()).into());
+    }
+    Ok(())
+  }
+}
+
 impl Engine for Pastebin {
   fn get_name(&self) -> &str {
     "pastebin"
   }
 
   fn get_domain(&self) -> &str {
-    "pastebin.com"
+    &self.domain
+  }
+
+  fn supports_private(&self) -> bool {
+    true
   }
 
-  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<String> {
+  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<Paste> {
     self.indexed_upload.upload(bins, data)
   }
 
-  fn get_raw(&self, bins: &Bins, url: &mut Url) -> Result<String> {
-    let new_path = {
-      String::from("/download") + url.path()
-    };
-    url.set_path(&new_path);
-    let mut headers = Headers::new();
-    headers.set(Referer(url.as_str().to_owned()));
-    let download = IndexedDownload {
-      url: String::from(url.as_str()),
-      headers: headers,
-      target: None
-    };
-    let downloaded = try!(download.download());
-    match self.check_index(bins, &downloaded) {
-      Ok(mut new_url) => return self.get_raw(bins, &mut new_url),
-      Err(e) => {
-        if let ErrorKind::InvalidIndexError = *e.kind() {} else {
-          return Err(e);
-        }
-      }
-    }
-    Ok(downloaded)
+  fn check_expiration(&self, bins: &Bins) -> Result<()> {
+    Pastebin::get_expire_date(bins).map(|_| ())
+  }
+
+  fn get_raw_url(&self, url: &Url) -> Result<Url> {
+    let mut raw_url = url.clone();
+    raw_url.set_path(&(String::from("/download") + url.path()));
+    Ok(raw_url)
+  }
+
+  fn get_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index(bins, raw, |u| self.get_raw(bins, u))
+  }
+
+  fn get_all_raw(&self, bins: &Bins, url: &Url) -> Result<Vec<RawPaste>> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index_to_all(bins, raw, |u| self.download_raw(bins, u))
+  }
+
+  fn delete(&self, bins: &Bins, url: &Url) -> Result<()> {
+    let downloaded = try!(self.download(bins, url));
+    self.delete_indexed(bins, url, &downloaded)
   }
 }
@@ -1,38 +1,54 @@
 use bins::error::*;
-use bins::{Bins, PasteFile};
+use bins::{Bins, Paste, PasteFile, RawPaste};
 use bins::engines::Engine;
 use hyper::client::Response;
 use bins::engines::indexed::{IndexedUpload, UploadsIndices, ProducesUrl, ProducesBody};
 use bins::engines::indexed::{ChecksIndices, IndexedDownload, DownloadsFile};
 use hyper::header::{Headers, ContentType};
+use bins::engines::lookup_url;
 use hyper::Url;
+use toml::Value;
 use url::form_urlencoded;
 
 pub struct Pastie {
+  domain: String,
   indexed_upload: IndexedUpload
 }
 
-unsafe impl Sync for Pastie {}
-
 impl Pastie {
-  pub fn new() -> Self {
-    let mut headers = Headers::new();
-    headers.set(ContentType::form_url_encoded());
-    Pastie {
+  pub fn new(config: &Value) -> Result<Self> {
+    let (base_url, domain) = try!(lookup_url(config, "pastie.base_url", "http://pastie.org"));
+    Ok(Pastie {
+      domain: domain,
       indexed_upload: IndexedUpload {
-        url: String::from("http://pastie.org/pastes"),
-        headers: headers,
+        url: base_url + "/pastes",
+        headers: vec![(String::from("Content-Type"), ContentType::form_url_encoded().to_string())],
         url_producer: Box::new(PastieUrlProducer {}),
         body_producer: Box::new(PastieBodyProducer {})
       }
-    }
+    })
+  }
+
+  /// Downloads a paste without following it if it's an index.
+  fn download_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw_url = try!(self.get_raw_url(url));
+    let download = IndexedDownload {
+      url: String::from(raw_url.as_str()),
+      headers: Headers::new(),
+      target: None
+    };
+    Ok(RawPaste {
+      url: raw_url.as_str().to_owned(),
+      file: None,
+      content: try!(download.download(bins))
+    })
   }
 }
 
 struct PastieUrlProducer { }
 
 impl ProducesUrl for PastieUrlProducer {
-  fn produce_url(&self, _: &Bins, res: Response, _: String) -> Result<String> {
+  fn produce_url(&self, _: &Bins, _: &PasteFile, res: Response, _: String) -> Result<String> {
     Ok(res.url.as_str().to_owned())
   }
 }
@@ -42,7 +58,7 @@ struct PastieBodyProducer { }
 impl ProducesBody for PastieBodyProducer {
   fn produce_body(&self, bins: &Bins, data: &PasteFile) -> Result<String> {
     Ok(form_urlencoded::Serializer::new(String::new())
-      .append_pair("paste[body]", &data.data)
+      .append_pair("paste[body]", &data.text())
       .append_pair("paste[authorization]", "burger")
       .append_pair("paste[restricted]",
                    if bins.arguments.private {
@@ -62,14 +78,18 @@ impl Engine for Pastie {
   }
 
   fn get_domain(&self) -> &str {
-    "pastie.org"
+    &self.domain
+  }
+
+  fn supports_private(&self) -> bool {
+    true
   }
 
-  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<String> {
+  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<Paste> {
     self.indexed_upload.upload(bins, data)
   }
 
-  fn get_raw(&self, bins: &Bins, url: &mut Url) -> Result<String> {
+  fn get_raw_url(&self, url: &Url) -> Result<Url> {
     let new_path = {
       let path = url.path();
       if path.starts_with("/private") {
@@ -82,21 +102,18 @@ impl Engine for Pastie {
         format!("/pastes{}/download", path)
       }
     };
-    url.set_path(&new_path);
-    let download = IndexedDownload {
-      url: String::from(url.as_str()),
-      headers: Headers::new(),
-      target: None
-    };
-    let downloaded = try!(download.download());
-    match self.check_index(bins, &downloaded) {
-      Ok(mut new_url) => return self.get_raw(bins, &mut new_url),
-      Err(e) => {
-        if let ErrorKind::InvalidIndexError = *e.kind() {} else {
-          return Err(e);
-        }
-      }
-    }
-    Ok(downloaded)
+    let mut raw_url = url.clone();
+    raw_url.set_path(&new_path);
+    Ok(raw_url)
+  }
+
+  fn get_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index(bins, raw, |u| self.get_raw(bins, u))
+  }
+
+  fn get_all_raw(&self, bins: &Bins, url: &Url) -> Result<Vec<RawPaste>> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index_to_all(bins, raw, |u| self.download_raw(bins, u))
   }
 }
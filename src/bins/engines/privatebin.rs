@@ -0,0 +1,503 @@
+use bins::error::*;
+use bins::{Bins, Paste, PastedFile, PasteFile, RawPaste};
+use bins::configuration::BetterLookups;
+use bins::engines::Engine;
+use bins::engines::indexed::{UploadsIndices, ChecksIndices};
+use bins::engines::lookup_url;
+use bins::expiration::Expiration;
+use bins::network::RetryPolicy;
+use crypto::aessafe::AesSafe256Encryptor;
+use crypto::ghash::Ghash;
+use crypto::hmac::Hmac;
+use crypto::pbkdf2::pbkdf2;
+use crypto::sha2::Sha256;
+use crypto::symmetriccipher::BlockEncryptor;
+use crypto::util::fixed_time_eq;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use hyper::header::{ContentType, Headers};
+use hyper::Url;
+use rand::{OsRng, Rng};
+use rustc_serialize::base64::{self, FromBase64, ToBase64};
+use rustc_serialize::json::{Json, ToJson};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::iter::repeat;
+use toml::Value;
+
+const EXPIRATIONS: &'static [(u64, &'static str)] = &[(300, "5min"),
+                                                       (600, "10min"),
+                                                       (3600, "1hour"),
+                                                       (86400, "1day"),
+                                                       (604800, "1week"),
+                                                       (2592000, "1month"),
+                                                       (31536000, "1year")];
+
+const BASE58_ALPHABET: &'static [u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+const ITERATIONS: u32 = 100000;
+
+pub struct PrivateBin {
+  domain: String,
+  url: String
+}
+
+impl PrivateBin {
+  pub fn new(config: &Value) -> Result<Self> {
+    let (base_url, domain) = try!(lookup_url(config, "privatebin.base_url", "https://privatebin.net"));
+    Ok(PrivateBin {
+      domain: domain,
+      url: base_url + "/"
+    })
+  }
+
+  fn get_expire(bins: &Bins) -> Result<&'static str> {
+    match bins.arguments.expire {
+      None | Some(Expiration::Never) => Ok("never"),
+      Some(Expiration::After(seconds)) => {
+        match EXPIRATIONS.iter().find(|e| e.0 == seconds) {
+          Some(e) => Ok(e.1),
+          None => {
+            let supported = EXPIRATIONS.iter().map(|e| Expiration::After(e.0).to_string()).collect::<Vec<_>>();
+            Err(format!("privatebin does not support an expiration of {} (supported: never, {})",
+                        Expiration::After(seconds),
+                        supported.join(", "))
+              .into())
+          }
+        }
+      }
+    }
+  }
+
+  fn get_headers() -> Headers {
+    let mut headers = Headers::new();
+    headers.set_raw("X-Requested-With", vec![b"JSONHttpRequest".to_vec()]);
+    headers
+  }
+
+  /// Encrypts a file, returning the paste without its `meta` and the key that goes in the url.
+  fn encrypt(file: &PasteFile, password: Option<&str>, burn: bool) -> Result<(BTreeMap<String, Json>, [u8; 32])> {
+    let mut rng = try!(OsRng::new());
+    let mut key = [0u8; 32];
+    rng.fill_bytes(&mut key);
+    let mut iv = [0u8; 16];
+    rng.fill_bytes(&mut iv);
+    let mut salt = [0u8; 8];
+    rng.fill_bytes(&mut salt);
+    let spec = vec![iv.to_base64(base64::STANDARD).to_json(),
+                    salt.to_base64(base64::STANDARD).to_json(),
+                    ITERATIONS.to_json(),
+                    256.to_json(),
+                    128.to_json(),
+                    "aes".to_json(),
+                    "gcm".to_json(),
+                    "zlib".to_json()];
+    let formatter = if file.language.is_some() {
+      "syntaxhighlighting"
+    } else {
+      "plaintext"
+    };
+    let adata = Json::Array(vec![Json::Array(spec), formatter.to_json(), 0.to_json(), (burn as u8).to_json()]);
+    let mut paste = BTreeMap::new();
+    if file.is_binary() {
+      // binary files are sent as attachments, which privatebin offers for download
+      let attachment = format!("data:{};base64,{}", file.mime, file.data.to_base64(base64::STANDARD));
+      paste.insert(String::from("paste"), "".to_json());
+      paste.insert(String::from("attachment"), attachment.to_json());
+      paste.insert(String::from("attachment_name"), file.name.to_json());
+    } else {
+      paste.insert(String::from("paste"), file.text().into_owned().to_json());
+    }
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::Default);
+    try!(encoder.write_all(Json::Object(paste).to_string().as_bytes()));
+    let compressed = try!(encoder.finish());
+    let derived = derive_key(&key, password, &salt, ITERATIONS);
+    let (mut ciphertext, tag) = gcm(&derived, &iv, adata.to_string().as_bytes(), &compressed, true);
+    ciphertext.extend(&tag);
+    let mut paste = BTreeMap::new();
+    paste.insert(String::from("v"), 2.to_json());
+    paste.insert(String::from("adata"), adata);
+    paste.insert(String::from("ct"), ciphertext.to_base64(base64::STANDARD).to_json());
+    Ok((paste, key))
+  }
+
+  /// Encrypts and uploads a file, returning its url and delete token.
+  fn create(&self, bins: &Bins, file: &PasteFile) -> Result<(String, String)> {
+    let burn = bins.arguments.burn || bins.config.lookup_bool_or("privatebin.burn_after_reading", false);
+    let password = bins.arguments.password.as_ref().map(|p| &p[..]);
+    let (mut body, key) = try!(PrivateBin::encrypt(file, password, burn));
+    let mut meta = BTreeMap::new();
+    meta.insert(String::from("expire"), try!(PrivateBin::get_expire(bins)).to_json());
+    body.insert(String::from("meta"), Json::Object(meta));
+    let body = Json::Object(body).to_string();
+    let client = try!(bins.network.get_client(&self.url));
+    let mut res = try!(RetryPolicy::new(&bins.config)
+      .send(false, || {
+        client.post(&self.url)
+          .headers(PrivateBin::get_headers())
+          .header(ContentType::json())
+          .body(&body)
+          .send()
+      })
+      .map_err(|e| e.to_string()));
+    let mut s = String::from("");
+    try!(res.read_to_string(&mut s).map_err(|e| e.to_string()));
+    let response = try!(PrivateBin::parse_response(&s));
+    let id = some_or_err!(response.find("id").and_then(|i| i.as_string()), "response had no id".into());
+    let delete_token = some_or_err!(response.find("deletetoken").and_then(|t| t.as_string()),
+                                    "response had no deletetoken".into());
+    Ok((format!("{}?{}#{}", self.url, id, base58_encode(&key)), delete_token.to_owned()))
+  }
+
+  fn parse_response(response: &str) -> Result<Json> {
+    let json = try!(Json::from_str(response).map_err(|e| format!("invalid response from privatebin: {}", e)));
+    if json.find("status").and_then(|s| s.as_u64()) != Some(0) {
+      let message = json.find("message").and_then(|m| m.as_string()).unwrap_or("unknown error");
+      return Err(format!("privatebin returned an error: {}", message).into());
+    }
+    Ok(json)
+  }
+
+  /// Decrypts a paste downloaded from the JSON API.
+  fn decrypt(paste: &Json, key: &[u8], password: Option<&str>) -> Result<Vec<u8>> {
+    if paste.find("v").and_then(|v| v.as_u64()) != Some(2) {
+      return Err("only version 2 privatebin pastes are supported".into());
+    }
+    let adata = some_or_err!(paste.find("adata"), "paste had no adata".into());
+    let spec = some_or_err!(adata.as_array().and_then(|a| a.get(0)).and_then(|s| s.as_array()),
+                            "paste had invalid adata".into());
+    let spec_str = |i: usize| spec.get(i).and_then(|s| s.as_string()).ok_or("paste had invalid adata");
+    let iv = try!(try!(spec_str(0)).from_base64().map_err(|e| e.to_string()));
+    let salt = try!(try!(spec_str(1)).from_base64().map_err(|e| e.to_string()));
+    let iterations = some_or_err!(spec.get(2).and_then(|i| i.as_u64()), "paste had invalid adata".into());
+    if try!(spec_str(5)) != "aes" || try!(spec_str(6)) != "gcm" || spec.get(3).and_then(|s| s.as_u64()) != Some(256) ||
+       spec.get(4).and_then(|s| s.as_u64()) != Some(128) {
+      return Err("paste uses an unsupported cipher".into());
+    }
+    let ct = some_or_err!(paste.find("ct").and_then(|c| c.as_string()), "paste had no ct".into());
+    let ct = try!(ct.from_base64().map_err(|e| e.to_string()));
+    if ct.len() < 16 {
+      return Err("paste ciphertext was too short".into());
+    }
+    let (ciphertext, tag) = ct.split_at(ct.len() - 16);
+    let derived = derive_key(key, password, &salt, iterations as u32);
+    let (compressed, expected_tag) = gcm(&derived, &iv, adata.to_string().as_bytes(), ciphertext, false);
+    if !fixed_time_eq(tag, &expected_tag) {
+      let hint = if password.is_none() {
+        " (use --password if the paste has one)"
+      } else {
+        ""
+      };
+      return Err(format!("paste could not be decrypted: wrong key or password{}", hint).into());
+    }
+    let plaintext = match try!(spec_str(7)) {
+      "zlib" => {
+        let mut decompressed = Vec::new();
+        try!(DeflateDecoder::new(&compressed[..]).read_to_end(&mut decompressed));
+        decompressed
+      }
+      "none" => compressed,
+      c => return Err(format!("paste uses an unsupported compression: {}", c).into()),
+    };
+    let plaintext = try!(String::from_utf8(plaintext).map_err(|e| e.to_string()));
+    let data = try!(Json::from_str(&plaintext).map_err(|e| e.to_string()));
+    if let Some(attachment) = data.find("attachment").and_then(|a| a.as_string()) {
+      let encoded = some_or_err!(attachment.find(";base64,").map(|i| &attachment[i + 8..]),
+                                 "paste had an invalid attachment".into());
+      return encoded.from_base64().map_err(|e| e.to_string().into());
+    }
+    let content = some_or_err!(data.find("paste").and_then(|p| p.as_string()), "paste had no text".into());
+    Ok(content.as_bytes().to_vec())
+  }
+
+  /// Downloads and decrypts a paste without following it if it's an index.
+  fn download_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let id = some_or_err!(url.query(), "privatebin url had no paste id".into()).to_owned();
+    // newer versions of privatebin start the key with - for pastes that are burned after reading
+    let key = some_or_err!(url.fragment(), "privatebin url had no key".into()).trim_left_matches('-');
+    let key = try!(base58_decode(key));
+    let mut api_url = url.clone();
+    api_url.set_fragment(None);
+    api_url.set_query(Some(&format!("pasteid={}", id)));
+    let api_url = api_url.as_str().to_owned();
+    let client = try!(bins.network.get_client(&api_url));
+    let mut res = try!(RetryPolicy::new(&bins.config)
+      .send(true, || client.get(&api_url).headers(PrivateBin::get_headers()).send())
+      .map_err(|e| e.to_string()));
+    let mut s = String::from("");
+    try!(res.read_to_string(&mut s).map_err(|e| e.to_string()));
+    let paste = try!(PrivateBin::parse_response(&s));
+    Ok(RawPaste {
+      url: api_url,
+      file: None,
+      content: try!(PrivateBin::decrypt(&paste, &key, bins.arguments.password.as_ref().map(|p| &p[..])))
+    })
+  }
+}
+
+/// Derives the AES key from the key in the url and the password, if any.
+///
+/// PrivateBin's JavaScript turns the password into bytes with `charCodeAt`, which keeps only the lowest byte of every
+/// UTF-16 code unit, so the password is encoded the same way instead of as UTF-8.
+fn derive_key(key: &[u8], password: Option<&str>, salt: &[u8], iterations: u32) -> Vec<u8> {
+  let mut secret = key.to_vec();
+  if let Some(password) = password {
+    secret.extend(password.encode_utf16().map(|unit| unit as u8));
+  }
+  let mut mac = Hmac::new(Sha256::new(), &secret);
+  let mut derived = vec![0u8; 32];
+  pbkdf2(&mut mac, salt, iterations, &mut derived);
+  derived
+}
+
+/// AES-256 in GCM mode, returning the output and the authentication tag.
+///
+/// PrivateBin uses 16-byte IVs, which the usual GCM implementations don't accept.
+fn gcm(key: &[u8], iv: &[u8], aad: &[u8], input: &[u8], encrypt: bool) -> (Vec<u8>, [u8; 16]) {
+  let cipher = AesSafe256Encryptor::new(key);
+  let mut h = [0u8; 16];
+  cipher.encrypt_block(&[0u8; 16], &mut h);
+  let mut j0 = [0u8; 16];
+  if iv.len() == 12 {
+    j0[..12].copy_from_slice(iv);
+    j0[15] = 1;
+  } else {
+    j0 = Ghash::new(&h).input_c(iv).result();
+  }
+  let mut counter = j0;
+  let mut keystream = [0u8; 16];
+  let mut output = Vec::with_capacity(input.len());
+  for chunk in input.chunks(16) {
+    // only the last 32 bits of the counter are incremented
+    for byte in counter[12..].iter_mut().rev() {
+      *byte = byte.wrapping_add(1);
+      if *byte != 0 {
+        break;
+      }
+    }
+    cipher.encrypt_block(&counter, &mut keystream);
+    output.extend(chunk.iter().zip(keystream.iter()).map(|(a, b)| a ^ b));
+  }
+  let tag = {
+    let ciphertext = if encrypt { &output[..] } else { input };
+    let mut tag = Ghash::new(&h).input_a(aad).input_c(ciphertext).result();
+    cipher.encrypt_block(&j0, &mut keystream);
+    for (t, k) in tag.iter_mut().zip(keystream.iter()) {
+      *t ^= *k;
+    }
+    tag
+  };
+  (output, tag)
+}
+
+fn base58_encode(data: &[u8]) -> String {
+  // digits in base 58, least significant first
+  let mut digits: Vec<u8> = Vec::new();
+  for &byte in data {
+    let mut carry = byte as u32;
+    for digit in &mut digits {
+      carry += (*digit as u32) << 8;
+      *digit = (carry % 58) as u8;
+      carry /= 58;
+    }
+    while carry > 0 {
+      digits.push((carry % 58) as u8);
+      carry /= 58;
+    }
+  }
+  let zeros = data.iter().take_while(|&&b| b == 0).count();
+  repeat('1').take(zeros).chain(digits.iter().rev().map(|&d| BASE58_ALPHABET[d as usize] as char)).collect()
+}
+
+fn base58_decode(string: &str) -> Result<Vec<u8>> {
+  // bytes, least significant first
+  let mut bytes: Vec<u8> = Vec::new();
+  for c in string.chars() {
+    let value = some_or_err!(BASE58_ALPHABET.iter().position(|&a| a as char == c),
+                             format!("invalid character {} in key", c).into());
+    let mut carry = value as u32;
+    for byte in &mut bytes {
+      carry += (*byte as u32) * 58;
+      *byte = carry as u8;
+      carry >>= 8;
+    }
+    while carry > 0 {
+      bytes.push(carry as u8);
+      carry >>= 8;
+    }
+  }
+  let mut decoded = vec![0u8; string.chars().take_while(|&c| c == '1').count()];
+  decoded.extend(bytes.iter().rev());
+  Ok(decoded)
+}
+
+impl UploadsIndices for PrivateBin {
+  fn real_upload(&self, bins: &Bins, data: &PasteFile) -> Result<String> {
+    self.create(bins, data).map(|(url, _)| url)
+  }
+}
+
+impl ChecksIndices for PrivateBin {}
+
+impl Engine for PrivateBin {
+  fn get_name(&self) -> &str {
+    "privatebin"
+  }
+
+  fn supports_binary(&self) -> bool {
+    true
+  }
+
+  fn supports_private(&self) -> bool {
+    true
+  }
+
+  fn get_domain(&self) -> &str {
+    &self.domain
+  }
+
+  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<Paste> {
+    if data.len() > 1 {
+      // the index is encrypted like every other paste, so the keys of the files stay secret
+      return UploadsIndices::upload(self, bins, data);
+    }
+    let (url, delete_token) = try!(self.create(bins, &data[0]));
+    Ok(Paste {
+      url: url.clone(),
+      raw_url: None,
+      files: vec![PastedFile::new(&data[0], url)],
+      delete_key: Some(delete_token)
+    })
+  }
+
+  fn get_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index(bins, raw, |u| self.get_raw(bins, u))
+  }
+
+  fn get_all_raw(&self, bins: &Bins, url: &Url) -> Result<Vec<RawPaste>> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index_to_all(bins, raw, |u| self.download_raw(bins, u))
+  }
+
+  fn check_expiration(&self, bins: &Bins) -> Result<()> {
+    PrivateBin::get_expire(bins).map(|_| ())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{PrivateBin, base58_decode, base58_encode, derive_key, gcm};
+  use bins::PasteFile;
+  use rustc_serialize::hex::FromHex;
+  use rustc_serialize::json::Json;
+
+  // encrypted with the cipher function of PrivateBin's client (js/privatebin.js) on WebCrypto, with the key
+  // 000102...1f and, for the second one, the password "pässwörd€"
+  const FIXTURE: &'static str =
+    concat!(r#"{"v":2,"adata":[["oKGio6SlpqeoqaqrrK2urw==","sLGys7S1trc=",100000,256,128,"aes","gcm","zlib"],"#,
+            r#""plaintext",0,0],"#,
+            r#""ct":"QroNcD/bbNpWlF2vt7JPgIyVz+6WROIkAtDbMbP3/fStSyXYtNlBUnoZzp49zLS8HcbaDQVZuxjt0cs=","#,
+            r#""meta":{"expire":"1week"}}"#);
+  const FIXTURE_WITH_PASSWORD: &'static str =
+    concat!(r#"{"v":2,"adata":[["oKGio6SlpqeoqaqrrK2urw==","sLGys7S1trc=",100000,256,128,"aes","gcm","zlib"],"#,
+            r#""plaintext",0,0],"#,
+            r#""ct":"ULHaewC1Dq49hbfSetm+iiy8X3cN67/5M4YIQCw7HBvcgJw0u0hxvMLm044Ab108qXHtY+8Pv6sGelE=","#,
+            r#""meta":{"expire":"1week"}}"#);
+
+  fn hex(string: &str) -> Vec<u8> {
+    string.from_hex().unwrap()
+  }
+
+  #[test]
+  fn gcm_known_answers_with_non_96_bit_ivs() {
+    // test cases 17 (64-bit iv) and 18 (480-bit iv) from the GCM specification
+    let key = hex("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308");
+    let plaintext = hex("d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e24\
+                         49a6b525b16aedf5aa0de657ba637b39");
+    let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+    let cases = [("cafebabefacedbad",
+                  "c3762df1ca787d32ae47c13bf19844cbaf1ae14d0b976afac52ff7d79bba9de0feb582d33934a4f0954cc2363bc73f78\
+                   62ac430e64abe499f47c9b1f",
+                  "3a337dbf46a792c45e454913fe2ea8f2"),
+                 ("9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728c3c0c95156809539fcf0e2429a6b5254\
+                   16aedbf5a0de6a57a637b39b",
+                  "5a8def2f0c9e53f1f75d7853659e2a20eeb2b22aafde6419a058ab4f6f746bf40fc0c3b780f244452da3ebf1c5d82cde\
+                   a2418997200ef82e44ae7e3f",
+                  "a44a8266ee1c8eb0c8b5d4cf5ae9f19a")];
+    for &(iv, ciphertext, tag) in &cases {
+      let (iv, ciphertext, tag) = (hex(iv), hex(ciphertext), hex(tag));
+      let (encrypted, encrypt_tag) = gcm(&key, &iv, &aad, &plaintext, true);
+      assert_eq!(encrypted, ciphertext);
+      assert_eq!(&encrypt_tag[..], &tag[..]);
+      let (decrypted, decrypt_tag) = gcm(&key, &iv, &aad, &ciphertext, false);
+      assert_eq!(decrypted, plaintext);
+      assert_eq!(&decrypt_tag[..], &tag[..]);
+    }
+  }
+
+  #[test]
+  fn encrypt_decrypt_round_trip() {
+    let file = PasteFile::new(String::from("hello.txt"), "Hello, world!");
+    let (paste, key) = PrivateBin::encrypt(&file, None, false).unwrap();
+    let paste = Json::Object(paste);
+    assert_eq!(PrivateBin::decrypt(&paste, &key, None).unwrap(), b"Hello, world!");
+    assert!(PrivateBin::decrypt(&paste, &key[1..], None).is_err());
+  }
+
+  #[test]
+  fn encrypt_decrypt_round_trip_with_password() {
+    let file = PasteFile::new(String::from("hello.txt"), "Hello, world!");
+    let (paste, key) = PrivateBin::encrypt(&file, Some("p\u{e4}ssword"), true).unwrap();
+    let paste = Json::Object(paste);
+    assert_eq!(PrivateBin::decrypt(&paste, &key, Some("p\u{e4}ssword")).unwrap(), b"Hello, world!");
+    assert!(PrivateBin::decrypt(&paste, &key, None).is_err());
+    assert!(PrivateBin::decrypt(&paste, &key, Some("password")).is_err());
+  }
+
+  #[test]
+  fn encrypt_decrypt_round_trip_of_attachment() {
+    let data = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR\x00\xff".to_vec();
+    let file = PasteFile::new(String::from("image.png"), data.clone());
+    assert!(file.is_binary());
+    let (paste, key) = PrivateBin::encrypt(&file, None, false).unwrap();
+    assert_eq!(PrivateBin::decrypt(&Json::Object(paste), &key, None).unwrap(), data);
+  }
+
+  #[test]
+  fn decrypts_privatebin_pastes() {
+    let key = (0..32).collect::<Vec<u8>>();
+    let expected = "{\"paste\":\"Hello from PrivateBin! \u{e4}\u{f6}\u{fc}\"}";
+    let paste = Json::from_str(FIXTURE).unwrap();
+    assert_eq!(PrivateBin::decrypt(&paste, &key, None).unwrap(), expected.as_bytes());
+    let paste = Json::from_str(FIXTURE_WITH_PASSWORD).unwrap();
+    assert_eq!(PrivateBin::decrypt(&paste, &key, Some("p\u{e4}ssw\u{f6}rd\u{20ac}")).unwrap(),
+               expected.as_bytes());
+    assert!(PrivateBin::decrypt(&paste, &key, Some("p\u{e4}ssw\u{f6}rd")).is_err());
+  }
+
+  #[test]
+  fn base58_round_trip() {
+    assert_eq!(base58_encode(b"Hello World!"), "2NEpo7TZRRrLZSi2U");
+    assert_eq!(base58_decode("2NEpo7TZRRrLZSi2U").unwrap(), b"Hello World!");
+    assert_eq!(base58_encode(&[0, 0, 0, 1, 2, 3]), "111Ldp");
+    let inputs: Vec<Vec<u8>> = vec![vec![], vec![0], vec![0, 0, 255], vec![0, 0, 0, 1, 2, 3], (0..32).collect(),
+                                    vec![255; 32]];
+    for input in inputs {
+      assert_eq!(base58_decode(&base58_encode(&input)).unwrap(), input);
+    }
+    assert!(base58_decode("0OIl").is_err());
+  }
+
+  #[test]
+  fn derive_key_encodes_passwords_like_privatebin() {
+    let key = (0..32).collect::<Vec<u8>>();
+    let salt = (0xb0..0xb8).collect::<Vec<u8>>();
+    assert_eq!(derive_key(&key, None, &salt, 1000),
+               "b33d19a0aadb7d826679ea626b6d7e6605a4cf4e4bf55e84b712a7f4d25462c5".from_hex().unwrap());
+    // ä and ö become 0xe4 and 0xf6, and € (U+20AC) becomes 0xac
+    assert_eq!(derive_key(&key, Some("p\u{e4}ssw\u{f6}rd\u{20ac}"), &salt, 1000),
+               "20d2ad0614e3e6d3f43ce06f37d4c5b295fe83b08c4dd7723e424f79caab1995".from_hex().unwrap());
+  }
+}
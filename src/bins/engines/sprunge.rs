@@ -1,37 +1,59 @@
 use bins::error::*;
-use bins::{Bins, PasteFile};
+use bins::{Bins, Paste, PasteFile, RawPaste};
 use bins::engines::Engine;
 use bins::engines::indexed::{IndexedUpload, UploadsIndices, ProducesUrl, ProducesBody};
 use bins::engines::indexed::{ChecksIndices, IndexedDownload, DownloadsFile};
 use hyper::client::Response;
+use bins::engines::lookup_url;
 use hyper::header::Headers;
 use hyper::Url;
+use toml::Value;
 use url::form_urlencoded;
 
 pub struct Sprunge {
+  domain: String,
   indexed_upload: IndexedUpload
 }
 
 impl Sprunge {
-  pub fn new() -> Self {
-    Sprunge {
+  pub fn new(config: &Value) -> Result<Self> {
+    let (base_url, domain) = try!(lookup_url(config, "sprunge.base_url", "http://sprunge.us"));
+    Ok(Sprunge {
+      domain: domain,
       indexed_upload: IndexedUpload {
-        url: String::from("http://sprunge.us"),
-        headers: Headers::new(),
+        url: base_url,
+        headers: Vec::new(),
         url_producer: Box::new(SprungeUrlProducer {}),
         body_producer: Box::new(SprungeBodyProducer {})
       }
-    }
+    })
   }
-}
 
-unsafe impl Sync for Sprunge {}
+  /// Downloads a paste without following it if it's an index.
+  fn download_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw_url = try!(self.get_raw_url(url));
+    let download = IndexedDownload {
+      url: String::from(raw_url.as_str()),
+      headers: Headers::new(),
+      target: None
+    };
+    Ok(RawPaste {
+      url: raw_url.as_str().to_owned(),
+      file: None,
+      content: try!(download.download(bins))
+    })
+  }
+}
 
 struct SprungeUrlProducer { }
 
 impl ProducesUrl for SprungeUrlProducer {
-  fn produce_url(&self, _: &Bins, _: Response, data: String) -> Result<String> {
-    Ok(data)
+  fn produce_url(&self, _: &Bins, file: &PasteFile, _: Response, data: String) -> Result<String> {
+    let url = data.trim();
+    match file.language {
+      Some(language) => Ok(format!("{}?{}", url, language.pygments)),
+      None => Ok(url.to_owned()),
+    }
   }
 }
 
@@ -40,7 +62,7 @@ struct SprungeBodyProducer { }
 impl ProducesBody for SprungeBodyProducer {
   fn produce_body(&self, _: &Bins, data: &PasteFile) -> Result<String> {
     Ok(form_urlencoded::Serializer::new(String::new())
-      .append_pair("sprunge", &data.data)
+      .append_pair("sprunge", &data.text())
       .finish())
   }
 }
@@ -53,30 +75,27 @@ impl Engine for Sprunge {
   }
 
   fn get_domain(&self) -> &str {
-    "sprunge.us"
+    &self.domain
   }
 
-  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<String> {
+  fn upload(&self, bins: &Bins, data: &[PasteFile]) -> Result<Paste> {
     self.indexed_upload.upload(bins, data)
   }
 
-  fn get_raw(&self, bins: &Bins, url: &mut Url) -> Result<String> {
+  fn get_raw_url(&self, url: &Url) -> Result<Url> {
     // Remove language specification to get raw text
-    url.set_query(None);
-    let download = IndexedDownload {
-      url: String::from(url.as_str()),
-      headers: Headers::new(),
-      target: None
-    };
-    let downloaded = try!(download.download());
-    match self.check_index(bins, &downloaded) {
-      Ok(mut new_url) => return self.get_raw(bins, &mut new_url),
-      Err(e) => {
-        if let ErrorKind::InvalidIndexError = *e.kind() {} else {
-          return Err(e);
-        }
-      }
-    }
-    Ok(downloaded)
+    let mut raw_url = url.clone();
+    raw_url.set_query(None);
+    Ok(raw_url)
+  }
+
+  fn get_raw(&self, bins: &Bins, url: &Url) -> Result<RawPaste> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index(bins, raw, |u| self.get_raw(bins, u))
+  }
+
+  fn get_all_raw(&self, bins: &Bins, url: &Url) -> Result<Vec<RawPaste>> {
+    let raw = try!(self.download_raw(bins, url));
+    self.follow_index_to_all(bins, raw, |u| self.download_raw(bins, u))
   }
 }
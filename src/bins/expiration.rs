@@ -0,0 +1,92 @@
+use bins::error::*;
+use std::fmt;
+
+const UNITS: &'static [(char, u64)] = &[('w', 604800), ('d', 86400), ('h', 3600), ('m', 60)];
+
+/// How long a paste should live before the service removes it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Expiration {
+  Never,
+  /// Expires after the given amount of seconds.
+  After(u64)
+}
+
+impl Expiration {
+  pub fn parse(string: &str) -> Result<Expiration> {
+    let string = string.trim().to_lowercase();
+    if string == "never" {
+      return Ok(Expiration::Never);
+    }
+    let invalid = || format!("invalid expiration \"{}\" (expected a duration like 10m, 1h, 1d, 1w or never)", string);
+    let unit = some_or_err!(string.chars().last(), invalid().into());
+    let number = &string[..string.len() - unit.len_utf8()];
+    let multiplier = some_or_err!(UNITS.iter().find(|u| u.0 == unit).map(|u| u.1), invalid().into());
+    let amount = try!(number.parse::<u64>().map_err(|_| invalid()));
+    if amount == 0 {
+      return Err(invalid().into());
+    }
+    let seconds = some_or_err!(amount.checked_mul(multiplier),
+                               format!("expiration \"{}\" is too long", string).into());
+    Ok(Expiration::After(seconds))
+  }
+}
+
+impl fmt::Display for Expiration {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Expiration::Never => write!(f, "never"),
+      Expiration::After(seconds) => {
+        let &(unit, multiplier) = UNITS.iter().find(|u| seconds % u.1 == 0).unwrap_or(&('m', 60));
+        write!(f, "{}{}", seconds / multiplier, unit)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Expiration;
+
+  #[test]
+  fn parse_durations() {
+    assert_eq!(Expiration::parse("10m").unwrap(), Expiration::After(600));
+    assert_eq!(Expiration::parse(" 2H ").unwrap(), Expiration::After(7200));
+    assert_eq!(Expiration::parse("1d").unwrap(), Expiration::After(86400));
+    assert_eq!(Expiration::parse("3w").unwrap(), Expiration::After(1814400));
+    assert_eq!(Expiration::parse("Never").unwrap(), Expiration::Never);
+  }
+
+  #[test]
+  fn parse_zero() {
+    assert!(Expiration::parse("0m").is_err());
+    assert!(Expiration::parse("0w").is_err());
+  }
+
+  #[test]
+  fn parse_overflow() {
+    assert!(Expiration::parse("99999999999999w").is_err());
+    assert!(Expiration::parse("99999999999999999999m").is_err());
+  }
+
+  #[test]
+  fn parse_invalid_units() {
+    for string in &["", "m", "10", "10y", "10 minutes", "-5m", "1.5h", "\u{e9}"] {
+      assert!(Expiration::parse(string).is_err(), "{:?} should be invalid", string);
+    }
+  }
+
+  #[test]
+  fn display_round_trip() {
+    for string in &["never", "10m", "90m", "1h", "36h", "1d", "2w"] {
+      let expiration = Expiration::parse(string).unwrap();
+      assert_eq!(expiration.to_string(), *string);
+      assert_eq!(Expiration::parse(&expiration.to_string()).unwrap(), expiration);
+    }
+  }
+
+  #[test]
+  fn display_uses_the_largest_unit() {
+    assert_eq!(Expiration::parse("60m").unwrap().to_string(), "1h");
+    assert_eq!(Expiration::parse("7d").unwrap().to_string(), "1w");
+  }
+}
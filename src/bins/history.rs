@@ -0,0 +1,115 @@
+use bins::error::*;
+use bins::{Bins, Paste, PasteFile};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use rustc_serialize::json;
+use time;
+
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct HistoryFile {
+  pub name: String,
+  pub size: usize
+}
+
+/// A paste that was successfully created, as recorded in the history file.
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct HistoryEntry {
+  pub url: String,
+  pub engine: String,
+  pub files: Vec<HistoryFile>,
+  pub private: bool,
+  /// Seconds since the Unix epoch.
+  pub timestamp: i64,
+  pub delete_key: Option<String>
+}
+
+impl HistoryEntry {
+  pub fn new(bins: &Bins, engine: &str, files: &[PasteFile], paste: &Paste) -> Self {
+    HistoryEntry {
+      url: paste.url.clone(),
+      engine: engine.to_owned(),
+      files: files.iter()
+        .map(|f| {
+          HistoryFile {
+            name: f.name.clone(),
+            size: f.data.len()
+          }
+        })
+        .collect(),
+      private: bins.arguments.private,
+      timestamp: time::get_time().sec,
+      delete_key: paste.delete_key.clone()
+    }
+  }
+
+  pub fn matches(&self, query: &str) -> bool {
+    let query = query.to_lowercase();
+    self.engine.to_lowercase().contains(&query) || self.files.iter().any(|f| f.name.to_lowercase().contains(&query))
+  }
+
+  pub fn format(&self, number: usize) -> String {
+    let tm = time::at(time::Timespec::new(self.timestamp, 0));
+    let date = time::strftime("%Y-%m-%d %H:%M", &tm).unwrap_or_else(|_| self.timestamp.to_string());
+    let files = self.files.iter().map(|f| f.name.as_ref()).collect::<Vec<&str>>().join(", ");
+    format!("{:>4}  {}  {:<8}  {:<7}  {}  ({})",
+            number,
+            date,
+            self.engine,
+            if self.private {
+              "private"
+            } else {
+              "public"
+            },
+            self.url,
+            files)
+  }
+}
+
+pub struct History {
+  path: PathBuf
+}
+
+impl History {
+  pub fn new() -> Result<Self> {
+    let mut path = match env::var("XDG_DATA_HOME") {
+      Ok(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+      _ => {
+        let mut home = some_or_err!(env::home_dir(), "could not find a home directory for history".into());
+        home.push(".local");
+        home.push("share");
+        home
+      }
+    };
+    path.push("bins");
+    path.push("history");
+    Ok(History { path: path })
+  }
+
+  pub fn append(&self, entry: &HistoryEntry) -> Result<()> {
+    if let Some(parent) = self.path.parent() {
+      try!(fs::create_dir_all(parent));
+    }
+    let line = try!(json::encode(entry).map_err(|e| e.to_string()));
+    let mut file = try!(OpenOptions::new().create(true).append(true).open(&self.path));
+    try!(writeln!(file, "{}", line));
+    Ok(())
+  }
+
+  pub fn read(&self) -> Result<Vec<HistoryEntry>> {
+    if !self.path.exists() {
+      return Ok(Vec::new());
+    }
+    let file = try!(File::open(&self.path));
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+      let line = try!(line);
+      if line.trim().is_empty() {
+        continue;
+      }
+      entries.push(try!(json::decode(&line)));
+    }
+    Ok(entries)
+  }
+}
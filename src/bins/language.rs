@@ -0,0 +1,67 @@
+use std::path::Path;
+
+/// A language used for syntax highlighting, along with the names services use for it.
+pub struct Language {
+  pub name: &'static str,
+  /// File extensions of the language. The first extension is used when a service needs one.
+  pub extensions: &'static [&'static str],
+  /// The value of `api_paste_format` on pastebin.
+  pub pastebin: &'static str,
+  /// The pygments lexer, used by sprunge.
+  pub pygments: &'static str
+}
+
+macro_rules! language {
+  ($name: expr, [$($ext: expr),+], $pastebin: expr, $pygments: expr) => {
+    Language { name: $name, extensions: &[$($ext),+], pastebin: $pastebin, pygments: $pygments }
+  }
+}
+
+pub static LANGUAGES: &'static [Language] = &[language!("bash", ["sh", "bash"], "bash", "bash"),
+                                              language!("c", ["c", "h"], "c", "c"),
+                                              language!("cpp", ["cpp", "cc", "cxx", "hpp"], "cpp", "cpp"),
+                                              language!("csharp", ["cs"], "csharp", "csharp"),
+                                              language!("css", ["css"], "css", "css"),
+                                              language!("diff", ["diff", "patch"], "diff", "diff"),
+                                              language!("go", ["go"], "go", "go"),
+                                              language!("haskell", ["hs"], "haskell", "haskell"),
+                                              language!("html", ["html", "htm"], "html5", "html"),
+                                              language!("java", ["java"], "java", "java"),
+                                              language!("javascript", ["js"], "javascript", "js"),
+                                              language!("json", ["json"], "json", "json"),
+                                              language!("lua", ["lua"], "lua", "lua"),
+                                              language!("markdown", ["md", "markdown"], "markdown", "md"),
+                                              language!("perl", ["pl", "pm"], "perl", "perl"),
+                                              language!("php", ["php"], "php", "php"),
+                                              language!("python", ["py"], "python", "python"),
+                                              language!("ruby", ["rb"], "ruby", "ruby"),
+                                              language!("rust", ["rs"], "rust", "rust"),
+                                              language!("scala", ["scala"], "scala", "scala"),
+                                              language!("sql", ["sql"], "sql", "sql"),
+                                              language!("toml", ["toml"], "ini", "toml"),
+                                              language!("xml", ["xml"], "xml", "xml"),
+                                              language!("yaml", ["yml", "yaml"], "yaml", "yaml")];
+
+impl Language {
+  pub fn get_extension(&self) -> &'static str {
+    self.extensions[0]
+  }
+}
+
+/// Finds a language by its name or by one of its extensions.
+pub fn get_language_by_name(name: &str) -> Option<&'static Language> {
+  let name = name.to_lowercase();
+  LANGUAGES.iter().find(|l| l.name == name || l.extensions.contains(&name.as_ref()))
+}
+
+pub fn get_language_for_file(name: &str) -> Option<&'static Language> {
+  let extension = match Path::new(name).extension() {
+    Some(e) => e.to_string_lossy().to_lowercase(),
+    None => return None,
+  };
+  LANGUAGES.iter().find(|l| l.extensions.contains(&extension.as_ref()))
+}
+
+pub fn get_language_names() -> Vec<&'static str> {
+  LANGUAGES.iter().map(|l| l.name).collect()
+}
@@ -2,62 +2,172 @@
 pub mod macros;
 pub mod error;
 pub mod arguments;
+pub mod binary;
 pub mod configuration;
 pub mod engines;
+pub mod expiration;
+pub mod history;
+pub mod language;
+pub mod network;
+pub mod output;
+pub mod paths;
+pub mod request;
+pub mod secrets;
 
 extern crate std;
 extern crate toml;
 
+use std::borrow::Cow;
 use std::io::prelude::*;
-use std::fs::File;
-use std::path::Path;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use toml::Value;
 use bins::error::*;
 use bins::arguments::Arguments;
+use bins::configuration::BetterLookups;
+use bins::history::{History, HistoryEntry};
 use bins::engines::Engine;
+use bins::language::{self, Language};
+use bins::network::Network;
+use bins::output::{self, Output, OutputMode, Template};
+use crossbeam;
 use hyper::Url;
 
 #[derive(Clone)]
 pub struct PasteFile {
   pub name: String,
-  pub data: String
+  pub data: Vec<u8>,
+  /// The MIME type detected from the data.
+  pub mime: &'static str,
+  pub language: Option<&'static Language>
 }
 
 impl PasteFile {
-  fn new(name: String, data: String) -> Self {
+  pub fn new<D: Into<Vec<u8>>>(name: String, data: D) -> Self {
+    let data = data.into();
+    let mime = binary::detect_mime(&data);
+    let language = if mime == binary::TEXT {
+      language::get_language_for_file(&name)
+    } else {
+      None
+    };
     PasteFile {
       name: name,
-      data: data
+      data: data,
+      mime: mime,
+      language: language
+    }
+  }
+
+  pub fn is_binary(&self) -> bool {
+    self.mime != binary::TEXT
+  }
+
+  /// Whether the file is in a binary format recognized by its magic number, as opposed to text or unknown data, which
+  /// may be text in an encoding other than UTF-8.
+  pub fn is_known_binary(&self) -> bool {
+    self.is_binary() && self.mime != binary::OCTET_STREAM
+  }
+
+  /// The data as text. Binary files are wrapped before they reach engines that only accept text, so nothing is lost
+  /// there.
+  pub fn text(&self) -> Cow<str> {
+    String::from_utf8_lossy(&self.data)
+  }
+
+  fn is_blank(&self) -> bool {
+    self.data.iter().all(|b| (*b as char).is_whitespace())
+  }
+}
+
+/// A file in a paste that was created.
+#[derive(RustcEncodable)]
+pub struct PastedFile {
+  pub name: String,
+  pub url: String,
+  pub size: usize
+}
+
+impl PastedFile {
+  pub fn new(file: &PasteFile, url: String) -> Self {
+    PastedFile {
+      name: file.name.clone(),
+      url: url,
+      size: file.data.len()
     }
   }
 }
 
+/// A file written to disk by `--output-dir`.
+#[derive(RustcEncodable)]
+pub struct DownloadedFile {
+  pub name: String,
+  pub url: String,
+  pub path: String,
+  pub size: usize
+}
+
+/// A paste that was created by an engine.
+pub struct Paste {
+  pub url: String,
+  /// The url of the raw contents of the paste, if the engine has one.
+  pub raw_url: Option<String>,
+  pub files: Vec<PastedFile>,
+  pub delete_key: Option<String>
+}
+
+/// A file downloaded from a paste in input mode.
+pub struct RawPaste {
+  /// The url the file was downloaded from.
+  pub url: String,
+  /// The name of the file, if it was chosen from a paste with several files.
+  pub file: Option<String>,
+  pub content: Vec<u8>
+}
+
 pub struct Bins {
   pub config: Value,
-  pub arguments: Arguments
+  pub arguments: Arguments,
+  pub engines: Vec<Box<Engine>>,
+  pub network: Network
 }
 
 impl Bins {
-  pub fn new(config: Value, arguments: Arguments) -> Self {
-    Bins {
+  pub fn new(config: Value, arguments: Arguments) -> Result<Self> {
+    let engines = try!(engines::get_engines(&config));
+    let network = Network::new(&config, arguments.timeout);
+    Ok(Bins {
       config: config,
-      arguments: arguments
-    }
+      arguments: arguments,
+      engines: engines,
+      network: network
+    })
   }
 
+  /// Gets the first service to paste to.
   pub fn get_engine(&self) -> Result<&Box<Engine>> {
-    let service = match self.arguments.service {
-      Some(ref s) => s,
-      None => return Err("no service was specified and no default service was set.".into()),
-    };
-    match engines::get_engine_by_name(service) {
-      Some(engine) => Ok(engine),
-      None => return Err(format!("unknown service \"{}\"", service).into()),
+    let engines = try!(self.get_selected_engines());
+    Ok(engines[0])
+  }
+
+  /// Gets every service to paste to, in the order they were given.
+  pub fn get_selected_engines(&self) -> Result<Vec<&Box<Engine>>> {
+    if self.arguments.services.is_empty() {
+      return Err("no service was specified and no default service was set.".into());
     }
+    let mut selected: Vec<&Box<Engine>> = Vec::new();
+    for service in &self.arguments.services {
+      let engine = some_or_err!(engines::get_engine_by_name(&self.engines, service),
+                                format!("unknown service \"{}\"", service).into());
+      if !selected.iter().any(|e| e.get_name() == engine.get_name()) {
+        selected.push(engine);
+      }
+    }
+    Ok(selected)
   }
 
-  fn read_file<P: AsRef<Path>>(&self, p: P) -> Result<String> {
+  fn read_file<P: AsRef<Path>>(&self, p: P) -> Result<Vec<u8>> {
     let path = p.as_ref();
     let name = some_or_err!(path.to_str(), "file name was not valid unicode".into());
     if !path.exists() {
@@ -70,55 +180,80 @@ impl Bins {
       Ok(f) => f,
       Err(e) => return Err(format!("could not open {}: {}", name, e).into()),
     };
-    let mut s = String::new();
-    if let Err(e) = file.read_to_string(&mut s) {
+    let mut data = Vec::new();
+    if let Err(e) = file.read_to_end(&mut data) {
       return Err(format!("could not read {}: {}", name, e).into());
     }
-    Ok(s)
+    Ok(data)
   }
 
-  fn read_file_to_pastefile<P: AsRef<Path>>(&self, p: P) -> Result<PasteFile> {
-    let path = p.as_ref();
-    match self.read_file(path) {
-      Ok(s) => {
-        let n = some_or_err!(path.file_name(), "not a valid file name".into());
-        Ok(PasteFile::new(n.to_string_lossy().into_owned(), s))
-      }
-      Err(s) => Err(s),
-    }
+  fn read_file_to_pastefile<P: AsRef<Path>>(&self, p: P, name: String) -> Result<PasteFile> {
+    self.read_file(p).map(|s| PasteFile::new(name, s))
+  }
+
+  /// Reads only the start of a file to tell whether it's binary.
+  fn is_binary_file(&self, path: &Path) -> Result<bool> {
+    let file = try!(File::open(path).map_err(|e| format!("could not open {}: {}", path.display(), e)));
+    let mut start = Vec::new();
+    try!(file.take(8192).read_to_end(&mut start).map_err(|e| format!("could not read {}: {}", path.display(), e)));
+    Ok(binary::starts_binary(&start))
   }
 
   pub fn get_to_paste(&self) -> Result<Vec<PasteFile>> {
     let arguments = &self.arguments;
     let message = &arguments.message;
-    let paste_files: Vec<PasteFile> = if message.is_some() {
+    let mut paste_files: Vec<PasteFile> = if message.is_some() {
       vec![PasteFile::new(String::from("message"), message.clone().unwrap())]
     } else if !arguments.files.is_empty() {
-      let files = arguments.files.clone();
-      let results = files.iter()
-        .map(|s| Path::new(s))
-        .map(|p| self.read_file_to_pastefile(p))
-        .map(|r| r.map_err(|e| e.iter().map(|x| x.to_string()).collect::<Vec<_>>().join("\n")))
-        .collect::<Vec<_>>();
-      for res in results.iter().cloned() {
-        if res.is_err() {
-          return Err(res.err().unwrap().into());
+      let found = try!(paths::expand(self, &arguments.files));
+      let max_files = self.config.lookup_int_or("defaults.max_files", 100);
+      let max_size = self.config.lookup_int_or("defaults.max_size", 1048576);
+      // files keep their paths relative to a common directory, so src/a/mod.rs and src/b/mod.rs stay apart
+      let names = paths::relative_names(&found.iter().map(|f| f.path.clone()).collect::<Vec<_>>());
+      let mut total_size = 0;
+      let mut pastes = Vec::new();
+      for (found_path, name) in found.into_iter().zip(names.into_iter()) {
+        // files found in directories or by patterns are only pasted if they're text, which is checked before reading
+        // all of them. only files that are pasted count towards the limits.
+        if !found_path.explicit && try!(self.is_binary_file(&found_path.path)) {
+          continue;
         }
+        let size = fs::metadata(&found_path.path).map(|m| m.len()).unwrap_or(0);
+        if max_size > 0 && (total_size + size) as i64 > max_size {
+          return Err(format!("files to paste are larger than defaults.max_size ({} bytes)", max_size).into());
+        }
+        let paste = try!(self.read_file_to_pastefile(&found_path.path, name));
+        if paste.is_blank() || (!found_path.explicit && paste.is_binary()) {
+          continue;
+        }
+        if max_files > 0 && pastes.len() as i64 >= max_files {
+          return Err(format!("found more than {} files to paste (defaults.max_files)", max_files).into());
+        }
+        total_size += size;
+        pastes.push(paste);
       }
-      let mut pastes =
-        results.iter().cloned().map(|r| r.unwrap()).filter(|p| !p.data.trim().is_empty()).collect::<Vec<_>>();
       self.handle_duplicate_file_names(&mut pastes);
       pastes
     } else {
-      let mut buffer = String::new();
-      if let Err(e) = std::io::stdin().read_to_string(&mut buffer) {
+      let mut buffer = Vec::new();
+      if let Err(e) = std::io::stdin().read_to_end(&mut buffer) {
         return Err(format!("error reading stdin: {}", e).into());
       }
       vec![PasteFile::new(String::from("stdin"), buffer)]
     };
-    if paste_files.iter().filter(|p| !p.data.trim().is_empty()).count() < 1 {
+    if paste_files.iter().filter(|p| !p.is_blank()).count() < 1 {
       return Err("no files (or all empty files) to paste".into());
     }
+    if let Some(ref lang) = arguments.lang {
+      let language = some_or_err!(language::get_language_by_name(lang),
+                                  format!("unknown language \"{}\" (available: {})",
+                                          lang,
+                                          language::get_language_names().join(", "))
+                                    .into());
+      for paste in &mut paste_files {
+        paste.language = Some(language);
+      }
+    }
     Ok(paste_files)
   }
 
@@ -147,28 +282,358 @@ impl Bins {
 
   fn get_engine_for_url<'a>(&'a self, url: &'a Url) -> Result<&Box<Engine>> {
     let domain = some_or_err!(url.domain(), "input url had no domain".into());
-    let engine = some_or_err!(engines::get_engine_by_domain(domain),
+    let engine = some_or_err!(engines::get_engine_by_domain(&self.engines, domain),
                               format!("could not find a bin for domain {}", domain).into());
     Ok(engine)
   }
 
-  fn get_raw(&self, url_string: &str) -> Result<String> {
+  fn parse_url(&self, url_string: &str) -> Result<Url> {
     // can't use try!() because url::parser is private, and ParseError is at url::parser::ParseError
-    let mut url = match Url::parse(url_string.as_ref()) {
-      Ok(u) => u,
-      Err(e) => return Err(e.to_string().into()),
+    match Url::parse(url_string.as_ref()) {
+      Ok(u) => Ok(u),
+      Err(e) => Err(e.to_string().into()),
+    }
+  }
+
+  fn get_raw(&self, url_string: &str) -> Result<RawPaste> {
+    let url = try!(self.parse_url(url_string));
+    let engine = try!(self.get_engine_for_url(&url));
+    let mut raw = try!(engine.get_raw(self, &url));
+    raw.content = try!(binary::unwrap(raw.content));
+    Ok(raw)
+  }
+
+  /// Names a downloaded file after its name in the paste, or after its url for pastes with a single file, which
+  /// usually have no name.
+  fn download_name(url: &Url, raw: &RawPaste) -> String {
+    match raw.file {
+      Some(ref name) => name.clone(),
+      None => {
+        let last = url.path_segments().and_then(|s| s.last()).unwrap_or("");
+        String::from(if last.is_empty() { "paste" } else { last })
+      }
+    }
+  }
+
+  fn write_download(&self, path: PathBuf, name: String, url: String, content: &[u8]) -> Result<DownloadedFile> {
+    if let Some(parent) = path.parent() {
+      try!(fs::create_dir_all(parent).map_err(|e| format!("could not create {}: {}", parent.display(), e)));
+    }
+    try!(File::create(&path)
+      .and_then(|mut f| f.write_all(content))
+      .map_err(|e| format!("could not write {}: {}", path.display(), e)));
+    Ok(DownloadedFile {
+      name: name,
+      url: url,
+      path: path.to_string_lossy().into_owned(),
+      size: content.len()
+    })
+  }
+
+  /// Downloads every file in a paste into `dir`, recreating the directories in their names. Existing files are only
+  /// overwritten with `--force`.
+  fn download_all(&self, url_string: &str, dir: &str) -> Result<Vec<DownloadedFile>> {
+    let url = try!(self.parse_url(url_string));
+    let engine = try!(self.get_engine_for_url(&url));
+    let raws = try!(engine.get_all_raw(self, &url));
+    let mut files = Vec::with_capacity(raws.len());
+    for raw in raws {
+      let name = Bins::download_name(&url, &raw);
+      let path = Path::new(dir).join(try!(paths::name_to_path(&name)));
+      files.push((name, raw.url, path, try!(binary::unwrap(raw.content))));
+    }
+    if !self.arguments.force {
+      let existing = files.iter().filter(|f| f.2.exists()).map(|f| f.2.to_string_lossy()).collect::<Vec<_>>();
+      if !existing.is_empty() {
+        return Err(format!("refusing to overwrite existing files, use --force to overwrite them:\n  {}",
+                           existing.join("\n  "))
+          .into());
+      }
+    }
+    files.into_iter().map(|(name, url, path, content)| self.write_download(path, name, url, &content)).collect()
+  }
+
+  /// Writes the file chosen in input mode to `target` byte for byte. If `target` is a directory, the file is written
+  /// into it under its name in the paste.
+  fn download_to_file(&self, url_string: &str, target: &str) -> Result<DownloadedFile> {
+    let url = try!(self.parse_url(url_string));
+    let raw = try!(self.get_raw(url_string));
+    let name = Bins::download_name(&url, &raw);
+    let target = Path::new(target);
+    let path = if target.is_dir() {
+      target.join(try!(paths::name_to_path(&name)))
+    } else {
+      target.to_path_buf()
     };
-    let url_clone = url.clone();
-    let engine = try!(self.get_engine_for_url(&url_clone));
-    engine.get_raw(self, &mut url)
+    if path.exists() && !self.arguments.force {
+      return Err(format!("refusing to overwrite {}, use --force to overwrite it", path.display()).into());
+    }
+    self.write_download(path, name, raw.url, &raw.content)
   }
 
-  pub fn get_output(&self) -> Result<String> {
+  /// Downloads every file in a paste to paste it again, keeping their names.
+  fn get_to_migrate(&self, url_string: &str) -> Result<Vec<PasteFile>> {
+    let url = try!(self.parse_url(url_string));
+    let engine = try!(self.get_engine_for_url(&url));
+    let mut files = Vec::new();
+    for raw in try!(engine.get_all_raw(self, &url)) {
+      let name = Bins::download_name(&url, &raw);
+      let file = PasteFile::new(name, try!(binary::unwrap(raw.content)));
+      if !file.is_blank() {
+        files.push(file);
+      }
+    }
+    if files.is_empty() {
+      return Err("paste to migrate had no files (or only empty files)".into());
+    }
+    self.handle_duplicate_file_names(&mut files);
+    Ok(files)
+  }
+
+  fn delete(&self, url_string: &str) -> Result<String> {
+    let url = try!(self.parse_url(url_string));
+    let engine = try!(self.get_engine_for_url(&url));
+    try!(engine.delete(self, &url));
+    Ok(format!("deleted {}", url))
+  }
+
+  fn update(&self, url_string: &str) -> Result<String> {
+    let url = try!(self.parse_url(url_string));
+    let engine = try!(self.get_engine_for_url(&url));
+    let arguments = &self.arguments;
+    let to_paste = if arguments.message.is_none() && arguments.files.is_empty() && !arguments.remove.is_empty() {
+      Vec::new()
+    } else {
+      try!(secrets::check(self, try!(self.get_to_paste())))
+    };
+    try!(engine.check_expiration(self));
+    let to_paste = try!(self.prepare_binary_files(engine.as_ref(), &to_paste));
+    engine.update(self, &url, &to_paste)
+  }
+
+  fn history(&self) -> Result<String> {
+    let entries = try!(try!(History::new()).read());
+    if let Some(recall) = self.arguments.recall {
+      let entry = some_or_err!(entries.get(recall), format!("no paste {} in history", recall).into());
+      return Ok(entry.url.clone());
+    }
+    let lines = entries.iter()
+      .enumerate()
+      .filter(|&(_, e)| self.arguments.search.as_ref().map_or(true, |q| e.matches(q)))
+      .map(|(i, e)| e.format(i))
+      .collect::<Vec<_>>();
+    if lines.is_empty() {
+      return Err("no pastes found in history".into());
+    }
+    Ok(lines.join("\n"))
+  }
+
+  fn record_history(&self, engine: &Engine, to_paste: &[PasteFile], paste: &Paste) {
+    if !self.config.lookup_bool_or("defaults.history", true) {
+      return;
+    }
+    let entry = HistoryEntry::new(self, engine.get_name(), to_paste, paste);
+    if let Err(e) = History::new().and_then(|h| h.append(&entry)) {
+      writeln!(std::io::stderr(), "could not record paste in history: {}", e).expect("error writing to stderr");
+    }
+  }
+
+  /// Wraps binary files for engines that only accept text, or refuses them if `defaults.binary` is "refuse".
+  fn prepare_binary_files(&self, engine: &Engine, files: &[PasteFile]) -> Result<Vec<PasteFile>> {
+    if engine.supports_binary() || !files.iter().any(|f| f.is_binary()) {
+      return Ok(files.to_vec());
+    }
+    match self.config.lookup_str_or("defaults.binary", "wrap") {
+      "wrap" => Ok(files.iter().map(|f| if f.is_binary() { binary::wrap(f) } else { f.clone() }).collect()),
+      "refuse" => {
+        let names = files.iter().filter(|f| f.is_binary()).map(|f| &f.name[..]).collect::<Vec<_>>();
+        Err(format!("{} only accepts text, but these files are binary: {}", engine.get_name(), names.join(", ")).into())
+      }
+      b => Err(format!("defaults.binary must be \"wrap\" or \"refuse\", not \"{}\"", b).into()),
+    }
+  }
+
+  pub fn upload(&self, engine: &Engine, to_paste: &[PasteFile]) -> Result<Paste> {
+    if to_paste.is_empty() {
+      return Err("no files to paste".into());
+    }
+    try!(engine.check_expiration(self));
+    let to_paste = try!(self.prepare_binary_files(engine, to_paste));
+    let mut paste = try!(engine.upload(self, &to_paste));
+    if paste.raw_url.is_none() {
+      paste.raw_url = Url::parse(&paste.url)
+        .ok()
+        .and_then(|u| engine.get_raw_url(&u).ok())
+        .map(|u| u.as_str().to_owned());
+    }
+    Ok(paste)
+  }
+
+  /// Gets the services in `--fallback` or `defaults.fallback` that can paste with the options given, leaving out
+  /// `first`.
+  fn get_fallback_engines(&self, first: &Engine) -> Result<Vec<&Box<Engine>>> {
+    let mut fallback: Vec<&Box<Engine>> = Vec::new();
+    for service in &self.arguments.fallback {
+      let engine = some_or_err!(engines::get_engine_by_name(&self.engines, service),
+                                format!("unknown fallback service \"{}\"", service).into());
+      if engine.get_name() == first.get_name() || fallback.iter().any(|e| e.get_name() == engine.get_name()) {
+        continue;
+      }
+      let skip = if self.arguments.private && !engine.supports_private() {
+        Some(String::from("it can't make private pastes"))
+      } else {
+        engine.check_expiration(self).err().map(|e| e.to_string())
+      };
+      match skip {
+        Some(reason) => {
+          writeln!(std::io::stderr(), "not falling back to {}: {}", engine.get_name(), reason)
+            .expect("error writing to stderr")
+        }
+        None => fallback.push(engine),
+      }
+    }
+    Ok(fallback)
+  }
+
+  /// Pastes to `engine`, trying the fallback services in order if it fails. Returns the service that accepted the
+  /// paste along with it.
+  fn upload_with_fallback<'a>(&'a self,
+                              engine: &'a Box<Engine>,
+                              to_paste: &[PasteFile])
+                              -> Result<(&'a Box<Engine>, Paste)> {
+    let mut engines = vec![engine];
+    let mut errors = Vec::new();
+    let mut i = 0;
+    while i < engines.len() {
+      let engine = engines[i];
+      match self.upload(engine.as_ref(), to_paste) {
+        Ok(paste) => {
+          if !errors.is_empty() {
+            writeln!(std::io::stderr(), "pasted to {} instead", engine.get_name()).expect("error writing to stderr");
+          }
+          return Ok((engine, paste));
+        }
+        Err(e) => {
+          // fallback services are only looked at once they're needed, so their warnings aren't printed every time
+          if i == 0 {
+            engines.extend(try!(self.get_fallback_engines(engine.as_ref())));
+            if engines.len() == 1 {
+              return Err(e);
+            }
+          }
+          let error = e.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(": ");
+          writeln!(std::io::stderr(), "{} failed: {}", engine.get_name(), error).expect("error writing to stderr");
+          errors.push(format!("{}: {}", engine.get_name(), error));
+        }
+      }
+      i += 1;
+    }
+    Err(format!("paste could not be created on any service:\n{}", errors.join("\n")).into())
+  }
+
+  /// Pastes to every service at once. A service failing doesn't stop the others.
+  fn upload_to_all(&self,
+                   engines: &[&Box<Engine>],
+                   to_paste: &[PasteFile])
+                   -> Vec<::std::result::Result<Paste, String>> {
+    crossbeam::scope(|scope| {
+      let handles = engines.iter()
+        .map(|engine| {
+          scope.spawn(move || {
+            self.upload(engine.as_ref(), to_paste)
+              .map_err(|e| e.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(": "))
+          })
+        })
+        .collect::<Vec<_>>();
+      handles.into_iter().map(|h| h.join()).collect()
+    })
+  }
+
+  /// Formats the url of a new paste as the output of bins, using `--format` or `--output` if given.
+  fn format_paste(&self, engine: &str, paste: &Paste) -> Result<String> {
+    match self.arguments.output {
+      OutputMode::Text => {
+        match self.arguments.format {
+          Some(ref format) => Ok(Template::new(format).format(self, engine, paste)),
+          None => Ok(paste.url.clone()),
+        }
+      }
+      OutputMode::Json => output::paste_to_json(self, engine, paste),
+    }
+  }
+
+  pub fn get_output(&self) -> Result<Output> {
+    if self.arguments.history {
+      return self.history().map(Output::Text);
+    }
     if let Some(ref input) = self.arguments.input {
-      return self.get_raw(input);
+      if let Some(ref dir) = self.arguments.output_dir {
+        let downloaded = try!(self.download_all(input, dir));
+        return match self.arguments.output {
+          OutputMode::Text => Ok(Output::Text(downloaded.iter().map(|f| &f.path[..]).collect::<Vec<_>>().join("\n"))),
+          OutputMode::Json => output::downloads_to_json(&downloaded).map(Output::Text),
+        };
+      }
+      if let Some(ref file) = self.arguments.output_file {
+        let downloaded = try!(self.download_to_file(input, file));
+        return match self.arguments.output {
+          OutputMode::Text => Ok(Output::Text(downloaded.path)),
+          OutputMode::Json => output::download_to_json(&downloaded).map(Output::Text),
+        };
+      }
+      let raw = try!(self.get_raw(input));
+      return match self.arguments.output {
+        OutputMode::Text => Ok(Output::Raw(raw.content)),
+        OutputMode::Json => output::raw_to_json(&raw).map(Output::Text),
+      };
+    }
+    if let Some(ref delete) = self.arguments.delete {
+      return self.delete(delete).map(Output::Text);
     }
-    let to_paste = try!(self.get_to_paste());
-    let engine = try!(self.get_engine());
-    engine.upload(self, &to_paste)
+    if let Some(ref update) = self.arguments.update {
+      return self.update(update).map(Output::Text);
+    }
+    let to_paste = match self.arguments.migrate {
+      Some(ref migrate) => try!(self.get_to_migrate(migrate)),
+      None => try!(self.get_to_paste()),
+    };
+    let to_paste = try!(secrets::check(self, to_paste));
+    let engines = try!(self.get_selected_engines());
+    if engines.len() == 1 {
+      let (engine, paste) = try!(self.upload_with_fallback(engines[0], &to_paste));
+      self.record_history(engine.as_ref(), &to_paste, &paste);
+      return self.format_paste(engine.get_name(), &paste).map(Output::Text);
+    }
+    let results = self.upload_to_all(&engines, &to_paste);
+    if results.iter().all(|r| r.is_err()) {
+      let errors = engines.iter()
+        .zip(results.iter())
+        .filter_map(|(e, r)| r.as_ref().err().map(|error| format!("{}: {}", e.get_name(), error)))
+        .collect::<Vec<_>>();
+      return Err(format!("paste could not be created on any service:\n{}", errors.join("\n")).into());
+    }
+    for (engine, result) in engines.iter().zip(results.iter()) {
+      if let Ok(ref paste) = *result {
+        self.record_history(engine.as_ref(), &to_paste, paste);
+      }
+    }
+    let output = match self.arguments.output {
+      OutputMode::Text => {
+        let mut lines = Vec::new();
+        for (engine, result) in engines.iter().zip(results.iter()) {
+          let line = match *result {
+            Ok(ref paste) => try!(self.format_paste(engine.get_name(), paste)),
+            Err(ref error) => format!("failed: {}", error),
+          };
+          lines.push(format!("{}: {}", engine.get_name(), line));
+        }
+        lines.join("\n")
+      }
+      OutputMode::Json => {
+        let names = engines.iter().map(|e| e.get_name()).collect::<Vec<_>>();
+        try!(output::results_to_json(self, &names, &results))
+      }
+    };
+    Ok(Output::Text(output))
   }
 }
@@ -0,0 +1,314 @@
+use bins::error::*;
+use bins::{Bins, DownloadedFile, Paste, PastedFile, RawPaste};
+use bins::binary;
+use rustc_serialize::base64::{self, ToBase64};
+use rustc_serialize::json::{self, Json, ToJson};
+use std::collections::BTreeMap;
+use std::str;
+
+/// What bins prints when it's done.
+pub enum Output {
+  /// A line of text, like the url of a paste.
+  Text(String),
+  /// The exact contents of a file, from input mode.
+  Raw(Vec<u8>)
+}
+
+/// How the results of bins are printed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum OutputMode {
+  /// Only the url of a paste, or the contents of a file in input mode.
+  Text,
+  /// A JSON object describing the result, for scripts.
+  Json
+}
+
+impl OutputMode {
+  pub fn parse(string: &str) -> Result<OutputMode> {
+    match &string.to_lowercase()[..] {
+      "text" => Ok(OutputMode::Text),
+      "json" => Ok(OutputMode::Json),
+      _ => Err(format!("unknown output mode \"{}\" (expected text or json)", string).into()),
+    }
+  }
+}
+
+/// How the values in a template are escaped.
+#[derive(Clone, Copy, PartialEq)]
+enum Escape {
+  Nothing,
+  Markdown,
+  Html,
+  Org
+}
+
+/// A template for the output of an upload, with `{url}`, `{raw_url}`, `{service}`, `{files}` and `{title}`
+/// placeholders.
+pub struct Template {
+  template: String,
+  escape: Escape
+}
+
+impl Template {
+  /// Gets a template by the name of a preset (markdown, html or org), or uses the string itself as the template.
+  pub fn new(template: &str) -> Template {
+    let (template, escape) = match &template.to_lowercase()[..] {
+      "markdown" | "md" => ("[{title}]({url})", Escape::Markdown),
+      "html" => ("<a href=\"{url}\">{title}</a>", Escape::Html),
+      "org" => ("[[{url}][{title}]]", Escape::Org),
+      _ => (template, Escape::Nothing),
+    };
+    Template {
+      template: template.to_owned(),
+      escape: escape
+    }
+  }
+
+  pub fn format(&self, bins: &Bins, engine: &str, paste: &Paste) -> String {
+    let files = paste.files.iter().map(|f| &f.name[..]).collect::<Vec<_>>().join(", ");
+    let title = bins.arguments.title.clone().unwrap_or_else(|| files.clone());
+    let values = [("url", paste.url.clone()),
+                  ("raw_url", paste.raw_url.clone().unwrap_or_default()),
+                  ("service", engine.to_owned()),
+                  ("files", files),
+                  ("title", title)];
+    self.format_values(&values)
+  }
+
+  /// Replaces the placeholders in one pass, so values are never expanded again. `{{` and `}}` are literal braces,
+  /// and unknown placeholders and unclosed braces are kept as they are.
+  fn format_values(&self, values: &[(&str, String)]) -> String {
+    let mut output = String::with_capacity(self.template.len());
+    let mut rest = &self.template[..];
+    while let Some(i) = rest.find(|c: char| c == '{' || c == '}') {
+      output.push_str(&rest[..i]);
+      let tail = &rest[i..];
+      if tail.starts_with("{{") || tail.starts_with("}}") {
+        output.push_str(&tail[..1]);
+        rest = &tail[2..];
+        continue;
+      }
+      let value = if tail.starts_with('{') {
+        tail.find('}').and_then(|end| values.iter().find(|v| v.0 == &tail[1..end]).map(|v| (end, v)))
+      } else {
+        None
+      };
+      match value {
+        Some((end, &(name, ref value))) => {
+          output.push_str(&self.escape_value(name, value));
+          rest = &tail[end + 1..];
+        }
+        None => {
+          output.push_str(&tail[..1]);
+          rest = &tail[1..];
+        }
+      }
+    }
+    output.push_str(rest);
+    output
+  }
+
+  fn escape_value(&self, name: &str, value: &str) -> String {
+    let is_url = name == "url" || name == "raw_url";
+    match self.escape {
+      Escape::Nothing => value.to_owned(),
+      Escape::Html => escape_html(value),
+      Escape::Markdown if is_url => escape_url(value, &['(', ')', '<', '>']),
+      Escape::Markdown => escape_markdown(value),
+      Escape::Org if is_url => escape_url(value, &['[', ']']),
+      // org itself replaces brackets in link descriptions with braces
+      Escape::Org => value.replace('[', "{").replace(']', "}"),
+    }
+  }
+}
+
+/// Percent-encodes whitespace and the given characters, which would end a link in markup.
+fn escape_url(string: &str, special: &[char]) -> String {
+  let mut escaped = String::with_capacity(string.len());
+  for c in string.chars() {
+    if c.is_whitespace() || special.contains(&c) {
+      let mut bytes = [0u8; 4];
+      for b in c.encode_utf8(&mut bytes).as_bytes() {
+        escaped.push_str(&format!("%{:02X}", b));
+      }
+    } else {
+      escaped.push(c);
+    }
+  }
+  escaped
+}
+
+fn escape_markdown(string: &str) -> String {
+  let mut escaped = String::with_capacity(string.len());
+  for c in string.chars() {
+    if "\\[]()*_`<>".contains(c) {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+fn escape_html(string: &str) -> String {
+  string.replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&#39;")
+}
+
+#[derive(RustcEncodable)]
+struct PasteOutput<'a> {
+  url: &'a str,
+  raw_url: Option<&'a str>,
+  files: &'a [PastedFile],
+  engine: &'a str,
+  private: bool,
+  delete_key: Option<&'a str>
+}
+
+impl<'a> PasteOutput<'a> {
+  fn new(bins: &Bins, engine: &'a str, paste: &'a Paste) -> Self {
+    PasteOutput {
+      url: &paste.url,
+      raw_url: paste.raw_url.as_ref().map(|u| &u[..]),
+      files: &paste.files,
+      engine: engine,
+      private: bins.arguments.private,
+      delete_key: paste.delete_key.as_ref().map(|k| &k[..])
+    }
+  }
+}
+
+/// Describes a paste that was just created.
+pub fn paste_to_json(bins: &Bins, engine: &str, paste: &Paste) -> Result<String> {
+  json::encode(&PasteOutput::new(bins, engine, paste)).map_err(|e| e.to_string().into())
+}
+
+#[derive(RustcEncodable)]
+struct ServiceOutput<'a> {
+  engine: &'a str,
+  /// The paste, if the service accepted it.
+  paste: Option<PasteOutput<'a>>,
+  error: Option<&'a str>
+}
+
+/// Describes the result of pasting to several services, with the paste or the error for each service.
+pub fn results_to_json(bins: &Bins,
+                       engines: &[&str],
+                       results: &[::std::result::Result<Paste, String>])
+                       -> Result<String> {
+  let outputs = engines.iter()
+    .zip(results.iter())
+    .map(|(&engine, result)| {
+      ServiceOutput {
+        engine: engine,
+        paste: result.as_ref().ok().map(|p| PasteOutput::new(bins, engine, p)),
+        error: result.as_ref().err().map(|e| &e[..])
+      }
+    })
+    .collect::<Vec<_>>();
+  json::encode(&outputs).map_err(|e| e.to_string().into())
+}
+
+#[derive(RustcEncodable)]
+struct RawOutput<'a> {
+  url: &'a str,
+  file: Option<&'a str>,
+  mime: &'a str,
+  /// "utf-8", or "base64" for binary files.
+  encoding: &'a str,
+  content: String
+}
+
+/// Describes a file that was downloaded in input mode.
+pub fn raw_to_json(raw: &RawPaste) -> Result<String> {
+  let mime = binary::detect_mime(&raw.content);
+  let (encoding, content) = match str::from_utf8(&raw.content) {
+    Ok(text) if mime == binary::TEXT => ("utf-8", text.to_owned()),
+    _ => ("base64", raw.content.to_base64(base64::STANDARD)),
+  };
+  let output = RawOutput {
+    url: &raw.url,
+    file: raw.file.as_ref().map(|f| &f[..]),
+    mime: mime,
+    encoding: encoding,
+    content: content
+  };
+  json::encode(&output).map_err(|e| e.to_string().into())
+}
+
+/// Describes the file written by `--output-file`.
+pub fn download_to_json(file: &DownloadedFile) -> Result<String> {
+  json::encode(file).map_err(|e| e.to_string().into())
+}
+
+/// Describes the files written by `--output-dir`.
+pub fn downloads_to_json(files: &[DownloadedFile]) -> Result<String> {
+  json::encode(&files).map_err(|e| e.to_string().into())
+}
+
+/// Describes an error and its causes, like `{"error": "...", "causes": ["..."]}`.
+pub fn error_to_json(error: &Error) -> String {
+  let mut messages = error.iter().map(|e| e.to_string());
+  let mut map = BTreeMap::new();
+  map.insert(String::from("error"), messages.next().to_json());
+  map.insert(String::from("causes"), messages.collect::<Vec<_>>().to_json());
+  Json::Object(map).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Template;
+
+  fn format(template: &str) -> String {
+    let values = [("url", String::from("https://example.com/a b(1)")),
+                  ("raw_url", String::from("https://example.com/raw/[1]")),
+                  ("service", String::from("gist")),
+                  ("files", String::from("{title}.txt, b.rs")),
+                  ("title", String::from("<a> [b] & *c*"))];
+    Template::new(template).format_values(&values)
+  }
+
+  #[test]
+  fn format_placeholders() {
+    assert_eq!(format("{service}: {url}"), "gist: https://example.com/a b(1)");
+    assert_eq!(format("{raw_url}{raw_url}"), "https://example.com/raw/[1]https://example.com/raw/[1]");
+    assert_eq!(format("no placeholders"), "no placeholders");
+    assert_eq!(format(""), "");
+  }
+
+  #[test]
+  fn format_does_not_expand_values() {
+    assert_eq!(format("{files}"), "{title}.txt, b.rs");
+  }
+
+  #[test]
+  fn format_escaped_braces() {
+    assert_eq!(format("{{url}}"), "{url}");
+    assert_eq!(format("{{{service}}}"), "{gist}");
+    assert_eq!(format("}}{{"), "}{");
+  }
+
+  #[test]
+  fn format_unknown_placeholders() {
+    assert_eq!(format("{nope} {service}"), "{nope} gist");
+    assert_eq!(format("{}{URL}"), "{}{URL}");
+  }
+
+  #[test]
+  fn format_unclosed_braces() {
+    assert_eq!(format("{service"), "{service");
+    assert_eq!(format("{ {service}"), "{ gist");
+    assert_eq!(format("{service} {"), "gist {");
+    assert_eq!(format("lone } brace"), "lone } brace");
+  }
+
+  #[test]
+  fn format_presets() {
+    assert_eq!(format("markdown"), "[\\<a\\> \\[b\\] & \\*c\\*](https://example.com/a%20b%281%29)");
+    assert_eq!(format("html"),
+               "<a href=\"https://example.com/a b(1)\">&lt;a&gt; [b] &amp; *c*</a>");
+    assert_eq!(format("org"), "[[https://example.com/a%20b(1)][<a> {b} & *c*]]");
+  }
+}
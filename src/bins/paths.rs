@@ -0,0 +1,216 @@
+use bins::error::*;
+use bins::Bins;
+use glob::{MatchOptions, Pattern};
+use ignore::WalkBuilder;
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+/// A file to paste, found from the files given on the command line.
+pub struct FoundPath {
+  pub path: PathBuf,
+  /// Whether the file was given by name, instead of being found in a directory or by a glob pattern.
+  pub explicit: bool
+}
+
+fn is_glob(string: &str) -> bool {
+  string.contains(|c: char| c == '*' || c == '?' || c == '[')
+}
+
+/// Finds every file in a directory, skipping ignored and hidden files unless asked not to.
+fn walk(bins: &Bins, root: &Path) -> Result<Vec<PathBuf>> {
+  let no_ignore = bins.arguments.no_ignore;
+  let walker = WalkBuilder::new(root)
+    .hidden(!bins.arguments.hidden)
+    .ignore(!no_ignore)
+    .git_ignore(!no_ignore)
+    .git_global(!no_ignore)
+    .git_exclude(!no_ignore)
+    .build();
+  let mut paths = Vec::new();
+  for entry in walker {
+    let entry = try!(entry.map_err(|e| e.to_string()));
+    if entry.file_type().map_or(false, |t| t.is_file()) {
+      paths.push(entry.path().to_path_buf());
+    }
+  }
+  paths.sort();
+  Ok(paths)
+}
+
+/// Expands a glob pattern by walking the directory before its first wildcard.
+fn expand_glob(bins: &Bins, glob: &str) -> Result<Vec<PathBuf>> {
+  let pattern = try!(Pattern::new(glob).map_err(|e| format!("invalid pattern {}: {}", glob, e)));
+  let mut root = PathBuf::new();
+  for component in Path::new(glob).components() {
+    if let Component::Normal(part) = component {
+      if is_glob(&part.to_string_lossy()) {
+        break;
+      }
+    }
+    root.push(component.as_os_str());
+  }
+  if root.as_os_str().is_empty() {
+    root.push(".");
+  }
+  let options = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: !bins.arguments.hidden
+  };
+  let paths = try!(walk(bins, &root));
+  // walking "." yields paths starting with "./", which patterns like "*.rs" don't
+  let matches = paths.into_iter()
+    .filter(|p| pattern.matches_path_with(p.strip_prefix(".").unwrap_or(p), &options))
+    .collect::<Vec<_>>();
+  if matches.is_empty() {
+    return Err(format!("no files matched {}", glob).into());
+  }
+  Ok(matches)
+}
+
+/// Expands the files given on the command line, following glob patterns and, with `--recursive`, directories.
+pub fn expand(bins: &Bins, files: &[String]) -> Result<Vec<FoundPath>> {
+  let mut found = Vec::new();
+  for file in files {
+    let path = Path::new(file);
+    if path.is_dir() {
+      if !bins.arguments.recursive {
+        return Err(format!("{} is a directory (use --recursive to paste the files in it)", file).into());
+      }
+      found.extend(try!(walk(bins, path)).into_iter().map(|p| FoundPath { path: p, explicit: false }));
+    } else if !path.exists() && is_glob(file) {
+      found.extend(try!(expand_glob(bins, file)).into_iter().map(|p| FoundPath { path: p, explicit: false }));
+    } else {
+      found.push(FoundPath {
+        path: path.to_path_buf(),
+        explicit: true
+      });
+    }
+  }
+  Ok(found)
+}
+
+/// Makes a path absolute and resolves `.` and `..` without touching the file system.
+fn normalize(path: &Path, current_dir: &Path) -> PathBuf {
+  let absolute = current_dir.join(path);
+  let mut normalized = PathBuf::new();
+  for component in absolute.components() {
+    match component {
+      Component::CurDir => {}
+      Component::ParentDir => {
+        normalized.pop();
+      }
+      c => normalized.push(c.as_os_str()),
+    }
+  }
+  normalized
+}
+
+/// Names files by their paths relative to the deepest directory containing all of them, separated by `/` on every
+/// platform. A single file is named by its file name.
+pub fn relative_names(paths: &[PathBuf]) -> Vec<String> {
+  relative_names_from(paths, &env::current_dir().unwrap_or_else(|_| PathBuf::new()))
+}
+
+fn relative_names_from(paths: &[PathBuf], current_dir: &Path) -> Vec<String> {
+  let paths = paths.iter().map(|p| normalize(p, current_dir)).collect::<Vec<_>>();
+  let mut root: Option<PathBuf> = None;
+  for path in &paths {
+    let parent = path.parent().map(|p| p.to_path_buf()).unwrap_or_else(PathBuf::new);
+    root = Some(match root {
+      Some(r) => {
+        r.components()
+          .zip(parent.components())
+          .take_while(|&(a, b)| a == b)
+          .map(|(a, _)| a.as_os_str())
+          .collect()
+      }
+      None => parent,
+    });
+  }
+  let root = root.unwrap_or_else(PathBuf::new);
+  // paths relative to / or to a directory above the current one would leak where the files are, so only the file
+  // names are used then
+  if root.parent().is_none() || (current_dir.starts_with(&root) && current_dir != root) {
+    return paths.iter()
+      .map(|p| p.file_name().map_or_else(String::new, |n| n.to_string_lossy().into_owned()))
+      .collect();
+  }
+  paths.iter()
+    .map(|p| {
+      p.strip_prefix(&root)
+        .unwrap_or(p.as_path())
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+    })
+    .collect()
+}
+
+/// Turns the name of a file in a paste back into a relative path, refusing names that would be written outside of
+/// the directory they're downloaded to.
+pub fn name_to_path(name: &str) -> Result<PathBuf> {
+  if name.starts_with('/') {
+    return Err(format!("refusing to write {} outside of the output directory", name).into());
+  }
+  let mut path = PathBuf::new();
+  for part in name.split('/').filter(|p| !p.is_empty() && *p != ".") {
+    // catches backslashes and drive letters on windows
+    let normal = Path::new(part).components().all(|c| if let Component::Normal(_) = c { true } else { false });
+    if !normal {
+      return Err(format!("refusing to write {} outside of the output directory", name).into());
+    }
+    path.push(part);
+  }
+  if path.as_os_str().is_empty() {
+    return Err(format!("{} is not a valid file name", name).into());
+  }
+  Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{name_to_path, relative_names_from};
+  use std::path::{Path, PathBuf};
+
+  fn names(paths: &[&str], current_dir: &str) -> Vec<String> {
+    relative_names_from(&paths.iter().map(PathBuf::from).collect::<Vec<_>>(), Path::new(current_dir))
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn relative_names() {
+    assert_eq!(names(&["src/main.rs"], "/p"), vec!["main.rs"]);
+    assert_eq!(names(&["src/a/mod.rs", "src/b/mod.rs"], "/p"), vec!["a/mod.rs", "b/mod.rs"]);
+    assert_eq!(names(&["a.txt", "sub/b.txt"], "/p"), vec!["a.txt", "sub/b.txt"]);
+    assert_eq!(names(&["./src/../src/a.rs", "/p/src/b/c.rs"], "/p"), vec!["a.rs", "b/c.rs"]);
+    assert_eq!(names(&["/q/a.txt", "/q/b/c.txt"], "/p"), vec!["a.txt", "b/c.txt"]);
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn relative_names_fall_back_to_file_names() {
+    // the common directory is /
+    assert_eq!(names(&["/etc/hosts", "/home/u/notes.txt"], "/tmp"), vec!["hosts", "notes.txt"]);
+    // the common directory is above the current one
+    assert_eq!(names(&["../a.txt", "b/c.txt"], "/home/u/p"), vec!["a.txt", "c.txt"]);
+  }
+
+  #[test]
+  fn name_to_path_accepts_relative_names() {
+    assert_eq!(name_to_path("a.txt").unwrap(), PathBuf::from("a.txt"));
+    assert_eq!(name_to_path("src/a/mod.rs").unwrap(), Path::new("src").join("a").join("mod.rs"));
+    assert_eq!(name_to_path("./a//b.txt").unwrap(), Path::new("a").join("b.txt"));
+  }
+
+  #[test]
+  fn name_to_path_refuses_names_outside_the_directory() {
+    assert!(name_to_path("../x").is_err());
+    assert!(name_to_path("/etc/x").is_err());
+    assert!(name_to_path("a/../../x").is_err());
+    assert!(name_to_path("a/..").is_err());
+    assert!(name_to_path("").is_err());
+    assert!(name_to_path("./").is_err());
+  }
+}
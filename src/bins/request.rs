@@ -0,0 +1,80 @@
+use bins::error::*;
+use bins::{Bins, Paste, PasteFile};
+use bins::arguments::Arguments;
+use bins::secrets;
+use std::collections::BTreeMap;
+use toml::Value;
+
+/// Builds and uploads a paste without going through the command line.
+pub struct PasteRequest {
+  files: Vec<PasteFile>,
+  service: Option<String>,
+  private: bool,
+  auth: bool,
+  allow_secrets: bool,
+  config: Value
+}
+
+impl PasteRequest {
+  pub fn new() -> Self {
+    PasteRequest {
+      files: Vec::new(),
+      service: None,
+      private: true,
+      auth: true,
+      allow_secrets: false,
+      config: Value::Table(BTreeMap::new())
+    }
+  }
+
+  pub fn file(mut self, file: PasteFile) -> Self {
+    self.files.push(file);
+    self
+  }
+
+  pub fn files<I: IntoIterator<Item = PasteFile>>(mut self, files: I) -> Self {
+    self.files.extend(files);
+    self
+  }
+
+  pub fn service<S: Into<String>>(mut self, service: S) -> Self {
+    self.service = Some(service.into());
+    self
+  }
+
+  pub fn private(mut self, private: bool) -> Self {
+    self.private = private;
+    self
+  }
+
+  pub fn auth(mut self, auth: bool) -> Self {
+    self.auth = auth;
+    self
+  }
+
+  /// Skips checking the files for secrets, which otherwise happens according to `secrets.mode` in the configuration.
+  pub fn allow_secrets(mut self, allow_secrets: bool) -> Self {
+    self.allow_secrets = allow_secrets;
+    self
+  }
+
+  /// Sets the configuration engines read api keys and tokens from, in the same format as the configuration file.
+  pub fn config(mut self, config: Value) -> Self {
+    self.config = config;
+    self
+  }
+
+  pub fn upload(self) -> Result<Paste> {
+    let arguments = Arguments {
+      services: self.service.into_iter().collect(),
+      private: self.private,
+      auth: self.auth,
+      allow_secrets: self.allow_secrets,
+      ..Arguments::default()
+    };
+    let bins = try!(Bins::new(self.config, arguments));
+    let engine = try!(bins.get_engine());
+    let files = try!(secrets::check(&bins, self.files));
+    bins.upload(engine.as_ref(), &files)
+  }
+}
@@ -0,0 +1,36 @@
+//! A library for pasting to and downloading from pastebin services.
+//!
+//! ```no_run
+//! use bins::{PasteFile, PasteRequest};
+//!
+//! let paste = PasteRequest::new()
+//!   .service("hastebin")
+//!   .file(PasteFile::new(String::from("hello.txt"), String::from("Hello, world!")))
+//!   .upload()
+//!   .unwrap();
+//! println!("{}", paste.url);
+//! ```
+
+extern crate toml;
+extern crate clap;
+extern crate hyper;
+extern crate rustc_serialize;
+extern crate url;
+#[macro_use]
+extern crate error_chain;
+extern crate linked_hash_map;
+extern crate regex;
+extern crate time;
+extern crate crossbeam;
+extern crate rand;
+extern crate crypto;
+extern crate flate2;
+extern crate glob;
+extern crate ignore;
+
+mod bins;
+
+pub use bins::{Bins, DownloadedFile, PasteFile, Paste, PastedFile, RawPaste};
+pub use bins::request::PasteRequest;
+pub use bins::{arguments, binary, configuration, engines, error, expiration, history, language, network, output,
+               paths, secrets};
@@ -1,23 +1,15 @@
-extern crate toml;
-extern crate clap;
-extern crate hyper;
-extern crate rustc_serialize;
-extern crate url;
-#[macro_use]
-extern crate error_chain;
+extern crate bins;
 #[cfg(feature = "clipboard_support")]
 extern crate clipboard;
-extern crate linked_hash_map;
-#[macro_use]
-extern crate lazy_static;
-
-mod bins;
+extern crate toml;
 
 use bins::error::*;
 use bins::Bins;
-use bins::arguments;
+use bins::arguments::{self, Arguments};
 use bins::configuration::{BinsConfiguration, Configurable};
+use bins::output::{self, Output, OutputMode};
 use std::io::Write;
+use toml::Value;
 #[cfg(feature = "clipboard_support")]
 use clipboard::ClipboardContext;
 
@@ -27,16 +19,26 @@ macro_rules! println_stderr {
 }
 
 macro_rules! or_exit {
-  ($expr: expr) => {
-    match $expr { Ok(x) => x, Err(e) => { for err in e.iter() { println_stderr!("{}", err); } return 1; } }
+  ($expr: expr, $json: expr) => {
+    match $expr { Ok(x) => x, Err(e) => { print_error(&e, $json); return 1; } }
   };
 }
 
-fn make_bins() -> Result<Bins> {
+fn print_error(error: &Error, json: bool) {
+  if json {
+    println_stderr!("{}", output::error_to_json(error));
+  } else {
+    for err in error.iter() {
+      println_stderr!("{}", err);
+    }
+  }
+}
+
+fn get_config_and_arguments() -> Result<(Value, Arguments)> {
   let configuration = BinsConfiguration::new();
   let config = try!(configuration.parse_config());
   let arguments = try!(arguments::get_arguments(&config));
-  Ok(Bins::new(config, arguments))
+  Ok((config, arguments))
 }
 
 #[cfg(feature = "clipboard_support")]
@@ -51,12 +53,28 @@ fn copy_to_clipboard(_: &str) -> Result<()> {
 }
 
 fn inner() -> i32 {
-  let bins = or_exit!(make_bins());
-  let output = or_exit!(bins.get_output());
-  if bins.arguments.copy {
-    or_exit!(copy_to_clipboard(&output));
+  let (config, arguments) = or_exit!(get_config_and_arguments(), false);
+  let json = arguments.output == OutputMode::Json;
+  let bins = or_exit!(Bins::new(config, arguments), json);
+  let output = or_exit!(bins.get_output(), json);
+  match output {
+    Output::Text(text) => {
+      if bins.arguments.copy {
+        or_exit!(copy_to_clipboard(&text), json);
+      }
+      println!("{}", text);
+    }
+    Output::Raw(data) => {
+      if bins.arguments.copy {
+        let text = or_exit!(String::from_utf8(data.clone()).map_err(|_| Error::from("binary files can't be copied")),
+                            json);
+        or_exit!(copy_to_clipboard(&text), json);
+      }
+      let stdout = std::io::stdout();
+      let mut stdout = stdout.lock();
+      or_exit!(stdout.write_all(&data).and_then(|_| stdout.flush()).map_err(Error::from), json);
+    }
   }
-  println!("{}", output);
   0
 }
 